@@ -0,0 +1,74 @@
+//! `#[derive(LcmsPixel)]` for the [`lcms2`](https://lib.rs/crates/lcms2) crate. Enable the `derive` feature of `lcms2` to use it.
+//!
+//! ```rust,ignore
+//! use lcms2::*;
+//!
+//! #[derive(Copy, Clone, LcmsPixel)]
+//! #[repr(C)]
+//! #[lcms2(format = CMYK_8)]
+//! struct Cmyk { c: u8, m: u8, y: u8, k: u8 }
+//! unsafe impl Pod for Cmyk {}
+//! unsafe impl Zeroable for Cmyk {}
+//! ```
+//!
+//! The format is a name of a `PixelFormat` constant, or any constant expression of type `PixelFormat`.
+//! The struct must be `#[repr(C)]` or `#[repr(transparent)]`, and its size must match the format (checked at compile time).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr};
+
+#[proc_macro_derive(LcmsPixel, attributes(lcms2))]
+pub fn derive_lcms_pixel(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !matches!(input.data, Data::Struct(_)) {
+        return Err(Error::new_spanned(ident, "LcmsPixel can only be derived for structs"));
+    }
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "LcmsPixel can't be derived for generic structs. Use `lcms2::impl_lcms_pixel!` for each concrete type"));
+    }
+
+    let mut has_repr = false;
+    let mut format = None;
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                    has_repr = true;
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("lcms2") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("format") {
+                    format = Some(meta.value()?.parse::<Expr>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `format = …`"))
+                }
+            })?;
+        }
+    }
+    if !has_repr {
+        return Err(Error::new_spanned(ident, "pixel structs need `#[repr(C)]` or `#[repr(transparent)]` to have a known layout"));
+    }
+    let format = match format {
+        // a bare constant name, like `RGB_8`
+        Some(Expr::Path(path)) if path.qself.is_none() && path.path.leading_colon.is_none() && path.path.segments.len() == 1 => {
+            let name = &path.path.segments[0].ident;
+            quote!(::lcms2::PixelFormat::#name)
+        },
+        Some(expr) => quote!(#expr),
+        None => return Err(Error::new_spanned(ident, "missing `#[lcms2(format = …)]` attribute with the PixelFormat of the struct")),
+    };
+
+    Ok(quote! {
+        ::lcms2::impl_lcms_pixel!(#ident => #format);
+    })
+}
@@ -1,15 +1,19 @@
-use crate::{ffi, Intent};
+use crate::memory::{MemHandlerPlugin, MemoryBudget};
+use crate::{ffi, Error, ErrorCode, ErrorDetails, Intent, LCMSResult, MemoryStats};
 use std::cell::UnsafeCell;
+use std::any::Any;
 use std::collections::HashMap;
 use std::ffi::CStr;
 use std::fmt;
 use std::mem;
-use std::os::raw::c_void;
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
 use std::panic::RefUnwindSafe;
 use std::panic::UnwindSafe;
 use std::ptr;
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// A special case for non-thread-aware functions.
 ///
@@ -87,6 +91,101 @@ impl Context for ThreadContext {
     }
 }
 
+type ErrorHandler = dyn Fn(ErrorCode, &str) + Send + Sync;
+
+/// Owned by `ThreadContext`, and stored as LCMS's user data of the context.
+///
+/// Objects created in the context can report errors from other threads, hence the locks.
+struct ContextData {
+    user_data: Option<Arc<dyn Any + Send + Sync>>,
+    error_handler: Mutex<Option<Arc<ErrorHandler>>>,
+    capture_errors: AtomicBool,
+    last_error: Mutex<Option<ErrorDetails>>,
+    memory: Option<Arc<MemoryBudget>>,
+}
+
+impl ContextData {
+    fn new() -> Self {
+        Self {
+            user_data: None,
+            error_handler: Mutex::new(None),
+            capture_errors: AtomicBool::new(false),
+            last_error: Mutex::new(None),
+            memory: None,
+        }
+    }
+
+    /// The context must have been created by `ThreadContext`
+    unsafe fn from_context<'a>(context: ffi::Context) -> Option<&'a Self> {
+        if context.is_null() {
+            return None;
+        }
+        ffi::cmsGetContextUserData(context).cast::<Self>().as_ref()
+    }
+
+    fn capturing(&self) -> bool {
+        self.capture_errors.load(Ordering::Relaxed)
+    }
+
+    fn take_last_error(&self) -> Option<ErrorDetails> {
+        self.last_error.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+impl Clone for ContextData {
+    fn clone(&self) -> Self {
+        Self {
+            user_data: self.user_data.clone(),
+            error_handler: Mutex::new(self.error_handler.lock().unwrap_or_else(|e| e.into_inner()).clone()),
+            capture_errors: AtomicBool::new(self.capturing()),
+            last_error: Mutex::new(None),
+            memory: self.memory.clone(),
+        }
+    }
+}
+
+/// Used by the allocator of contexts with a memory limit
+pub(crate) unsafe fn memory_budget<'a>(context: ffi::Context) -> Option<&'a MemoryBudget> {
+    ContextData::from_context(context)?.memory.as_deref()
+}
+
+unsafe extern "C" fn log_error(context: ffi::Context, code: u32, text: *const c_char) {
+    let Some(data) = ContextData::from_context(context) else { return };
+    let code = ErrorCode::from(code);
+    let message = if text.is_null() { "".into() } else { CStr::from_ptr(text).to_string_lossy() };
+
+    let handler = data.error_handler.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(handler) = handler {
+        // can't unwind into C
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(code, &message)));
+    }
+    if data.capturing() {
+        *data.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(ErrorDetails { code, message: message.into_owned(), function: "" });
+    }
+}
+
+/// Creates an object in the context, and if that fails, replaces `ObjectCreationError` with the error reported by LCMS (if the context captures errors).
+///
+/// `function` is the name of the constructor, for `ErrorDetails`.
+pub(crate) fn capture_errors<T>(context: ffi::Context, function: &'static str, create: impl FnOnce() -> LCMSResult<T>) -> LCMSResult<T> {
+    let memory = unsafe { memory_budget(context) };
+    let failed_allocations = memory.map(|m| m.stats().failed);
+    let data = unsafe { ContextData::from_context(context) }.filter(|d| d.capturing());
+    if let Some(data) = data {
+        data.take_last_error();
+    }
+    let res = create();
+    if res.is_err() && memory.map(|m| m.stats().failed) != failed_allocations {
+        return Err(Error::MemoryLimitExceeded);
+    }
+    match res {
+        Err(Error::ObjectCreationError) => Err(data.and_then(|d| d.take_last_error()).map_or(Error::ObjectCreationError, |details| {
+            Error::from_details(ErrorDetails { function, ..details })
+        })),
+        res => res,
+    }
+}
+
 /// Per-thread context for multi-threaded operation.
 ///
 /// There are situations where several instances of Little CMS engine have to coexist but on different conditions.
@@ -126,19 +225,79 @@ impl ThreadContext {
     #[inline]
     #[must_use]
     pub fn new() -> Self {
-        unsafe { Self::new_handle(ffi::cmsCreateContext(ptr::null_mut(), ptr::null_mut())) }
+        Self::with_data(ContextData::new()).unwrap()
     }
 
+    /// Allows setting user data, plugins and other options before the context is created.
+    pub fn builder() -> ThreadContextBuilder {
+        ThreadContextBuilder::default()
+    }
+
+    /// Creates a context that refuses to allocate more than `bytes` of memory in total.
+    ///
+    /// This protects from profiles that request huge lookup tables. Constructors return `Error::MemoryLimitExceeded` when they run out of the budget.
+    /// The limit is shared with clones of this context. It doesn't include the context itself.
+    ///
+    /// Panics if the limit is too small to create the context. See `try_with_memory_limit()`.
     #[track_caller]
+    #[must_use]
+    pub fn with_memory_limit(bytes: usize) -> Self {
+        Self::try_with_memory_limit(bytes).unwrap()
+    }
+
+    /// Like `with_memory_limit()`, but returns `Error::MemoryLimitExceeded` if the context itself doesn't fit in the limit
+    pub fn try_with_memory_limit(bytes: usize) -> LCMSResult<Self> {
+        let mut data = ContextData::new();
+        data.memory = Some(Arc::new(MemoryBudget::new(bytes)));
+        Self::with_data(data)
+    }
+
+    /// Like `clone()`, but returns `Error::MemoryLimitExceeded` instead of panicking when the context has no memory left for the copy
+    pub fn try_clone(&self) -> LCMSResult<Self> {
+        unsafe { Self::new_handle(|data| ffi::cmsDupContext(self.handle, data), self.data().clone()) }
+    }
+
+    fn with_data(data: ContextData) -> LCMSResult<Self> {
+        if data.memory.is_some() {
+            let mut plugin = MemHandlerPlugin::new();
+            unsafe { Self::new_handle(|data| ffi::cmsCreateContext((&mut plugin as *mut MemHandlerPlugin).cast(), data), data) }
+        } else {
+            unsafe { Self::new_handle(|data| ffi::cmsCreateContext(ptr::null_mut(), data), data) }
+        }
+    }
+
+    /// Memory usage, if the context has been created with `with_memory_limit()`
+    #[must_use]
+    pub fn memory_stats(&self) -> Option<MemoryStats> {
+        self.data().memory.as_ref().map(|m| m.stats())
+    }
+
     #[inline]
-    unsafe fn new_handle(handle: ffi::Context) -> Self {
-        assert!(!handle.is_null());
-        Self { handle }
+    unsafe fn new_handle(create: impl FnOnce(*mut c_void) -> ffi::Context, data: ContextData) -> LCMSResult<Self> {
+        let failed_allocations = data.memory.as_ref().map(|m| m.stats().failed);
+        let data = Box::into_raw(Box::new(data));
+        let handle = create(data.cast());
+        if handle.is_null() {
+            let data = Box::from_raw(data);
+            if data.memory.as_ref().map(|m| m.stats().failed) != failed_allocations {
+                return Err(Error::MemoryLimitExceeded);
+            }
+            return Err(Error::ObjectCreationError);
+        }
+        Ok(Self { handle })
     }
 
+    #[inline]
+    fn data(&self) -> &ContextData {
+        unsafe { ContextData::from_context(self.handle).unwrap() }
+    }
+
+    /// User data set with `ThreadContext::builder().user_data()`. Returns `None` if there's none, or it's of a different type.
+    ///
+    /// Clones of the context share the same user data.
     #[must_use]
-    pub fn user_data(&self) -> *mut c_void {
-        unsafe { ffi::cmsGetContextUserData(self.handle) }
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        self.data().user_data.as_deref()?.downcast_ref()
     }
 
     pub unsafe fn install_plugin(&mut self, plugin: *mut c_void) -> bool {
@@ -218,23 +377,169 @@ impl ThreadContext {
     }
 
     /// Sets a function to be called if there is an error.
+    ///
+    /// This replaces the handler set with `set_error_handler()` and disables `set_error_capture()`.
     pub fn set_error_logging_function(&mut self, handler: ffi::LogErrorHandlerFunction) {
+        // errors won't reach `log_error` any more
+        *self.data().error_handler.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.set_error_capture(false);
         unsafe {
             ffi::cmsSetLogErrorHandlerTHR(self.handle, handler);
         }
     }
+
+    /// Sets a closure to be called with errors reported by LCMS in this context, and by objects created in it.
+    ///
+    /// The closure may be called from any thread that uses objects of this context. Panics in it are ignored.
+    pub fn set_error_handler(&mut self, handler: impl Fn(ErrorCode, &str) + Send + Sync + 'static) {
+        self.set_error_handler_arc(Arc::new(handler));
+    }
+
+    fn set_error_handler_arc(&mut self, handler: Arc<ErrorHandler>) {
+        *self.data().error_handler.lock().unwrap_or_else(|e| e.into_inner()) = Some(handler);
+        self.install_error_logger();
+    }
+
+    /// When enabled, the most recent error reported by LCMS is kept, and returned as a specific `Error` variant with `ErrorDetails` from constructors
+    /// like `Profile::new_icc_context()` and `Transform::new_flags_context()` instead of an unhelpful `Error::ObjectCreationError`.
+    pub fn set_error_capture(&mut self, enabled: bool) {
+        self.data().capture_errors.store(enabled, Ordering::Relaxed);
+        if enabled {
+            self.install_error_logger();
+        } else {
+            self.data().take_last_error();
+        }
+    }
+
+    /// Takes the most recent error reported by LCMS. Requires `set_error_capture(true)`.
+    #[must_use]
+    pub fn last_error(&self) -> Option<ErrorDetails> {
+        self.data().take_last_error()
+    }
+
+    fn install_error_logger(&mut self) {
+        unsafe {
+            ffi::cmsSetLogErrorHandlerTHR(self.handle, Some(log_error));
+        }
+    }
+}
+
+/// Options for a new `ThreadContext`. See [`ThreadContext::builder`].
+#[derive(Default)]
+#[must_use]
+pub struct ThreadContextBuilder {
+    user_data: Option<Arc<dyn Any + Send + Sync>>,
+    plugins: Vec<*mut c_void>,
+    adaptation_state: Option<f64>,
+    alarm_codes: Option<[u16; ffi::MAXCHANNELS]>,
+    memory_limit: Option<usize>,
+    error_handler: Option<Arc<ErrorHandler>>,
+    error_capture: bool,
+}
+
+impl ThreadContextBuilder {
+    /// Data that can be retrieved later with `ThreadContext::user_data()`.
+    ///
+    /// It's shared between clones of the context, and dropped with the last one.
+    ///
+    /// The data must be `Sync`, because `ThreadContext::user_data()` returns a reference to it on any thread
+    /// that has a clone of the context (or a `&ThreadContext`), so several threads can read it at the same time.
+    /// Wrap data that is only `Send` in a `Mutex`.
+    pub fn user_data<T: Send + Sync + 'static>(mut self, data: T) -> Self {
+        self.user_data = Some(Arc::new(data));
+        self
+    }
+
+    /// A plugin to install. See `ThreadContext::install_plugin()`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a valid LCMS plugin structure.
+    pub unsafe fn plugin(mut self, plugin: *mut c_void) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// See `ThreadContext::set_adaptation_state()`
+    pub fn adaptation_state(mut self, value: f64) -> Self {
+        self.adaptation_state = Some(value);
+        self
+    }
+
+    /// See `ThreadContext::set_alarm_codes()`
+    pub fn alarm_codes(mut self, codes: [u16; ffi::MAXCHANNELS]) -> Self {
+        self.alarm_codes = Some(codes);
+        self
+    }
+
+    /// See `ThreadContext::with_memory_limit()`
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// See `ThreadContext::set_error_handler()`
+    pub fn error_handler(mut self, handler: impl Fn(ErrorCode, &str) + Send + Sync + 'static) -> Self {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// See `ThreadContext::set_error_capture()`
+    pub fn error_capture(mut self, enabled: bool) -> Self {
+        self.error_capture = enabled;
+        self
+    }
+
+    /// Fails if the context doesn't fit in the memory limit, or if any of the plugins couldn't be installed
+    pub fn build(self) -> LCMSResult<ThreadContext> {
+        let mut data = ContextData::new();
+        data.user_data = self.user_data;
+        data.memory = self.memory_limit.map(|bytes| Arc::new(MemoryBudget::new(bytes)));
+        let mut context = ThreadContext::with_data(data)?;
+        for plugin in self.plugins {
+            if !unsafe { context.install_plugin(plugin) } {
+                return Err(Error::ObjectCreationError);
+            }
+        }
+        if let Some(value) = self.adaptation_state {
+            context.set_adaptation_state(value);
+        }
+        if let Some(codes) = self.alarm_codes {
+            context.set_alarm_codes(codes);
+        }
+        if let Some(handler) = self.error_handler {
+            context.set_error_handler_arc(handler);
+        }
+        context.set_error_capture(self.error_capture);
+        Ok(context)
+    }
+}
+
+impl fmt::Debug for ThreadContextBuilder {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ThreadContextBuilder")
+    }
 }
 
 impl Clone for ThreadContext {
+    /// Panics if the context has a memory limit that doesn't leave room for the copy. See `try_clone()`.
     #[inline]
+    #[track_caller]
     fn clone(&self) -> Self {
-        unsafe { Self::new_handle(ffi::cmsDupContext(self.handle, ptr::null_mut())) }
+        self.try_clone().unwrap()
     }
 }
 
 impl Drop for ThreadContext {
     fn drop(&mut self) {
-        unsafe { ffi::cmsDeleteContext(self.handle) }
+        unsafe {
+            let data = ffi::cmsGetContextUserData(self.handle).cast::<ContextData>();
+            ffi::cmsDeleteContext(self.handle);
+            if !data.is_null() {
+                drop(Box::from_raw(data));
+            }
+        }
     }
 }
 
@@ -269,7 +574,7 @@ impl fmt::Debug for GlobalContext {
 #[test]
 fn context() {
     let mut c = ThreadContext::new();
-    assert!(c.user_data().is_null());
+    assert!(c.user_data::<u8>().is_none());
     c.unregister_plugins();
     assert!(crate::Profile::new_icc_context(&c, &[]).is_err());
 
@@ -277,3 +582,95 @@ fn context() {
 
     let _ = GlobalContext::default();
 }
+
+#[test]
+fn builder() {
+    let mut codes = [0; ffi::MAXCHANNELS];
+    codes[1] = 123;
+    let c = ThreadContext::builder()
+        .user_data(String::from("hello"))
+        .adaptation_state(0.5)
+        .alarm_codes(codes)
+        .memory_limit(10_000_000)
+        .error_capture(true)
+        .build().unwrap();
+    assert_eq!("hello", c.user_data::<String>().unwrap());
+    assert!(c.user_data::<u8>().is_none());
+    assert_eq!(0.5, c.adaptation_state());
+    assert_eq!(123, c.alarm_codes()[1]);
+    assert!(c.memory_stats().is_some());
+
+    let c2 = c.clone();
+    drop(c);
+    assert_eq!("hello", c2.user_data::<String>().unwrap());
+    assert!(matches!(crate::Profile::new_icc_context(&c2, &[0; 200]), Err(Error::CorruptProfile(_))));
+}
+
+#[test]
+fn memory_limit() {
+    let c = ThreadContext::with_memory_limit(2_000_000);
+    let before = c.memory_stats().unwrap();
+    assert!(before.current > 0);
+    let p = crate::Profile::new_bchsw_abstract_context(&c, 17, 0., 1., 0., 1., None).unwrap();
+    let stats = c.memory_stats().unwrap();
+    assert!(stats.current > before.current);
+    assert_eq!(0, stats.failed);
+    let c2 = c.clone();
+    drop(p);
+    assert_eq!(Err(Error::MemoryLimitExceeded), crate::Profile::new_bchsw_abstract_context(&c2, 255, 0., 1., 0., 1., None).map(drop));
+    let stats = c.memory_stats().unwrap();
+    assert!(stats.failed > 0);
+    assert!(stats.peak <= 2_000_000);
+    assert!(ThreadContext::new().memory_stats().is_none());
+}
+
+#[test]
+fn memory_limit_exhausted() {
+    let size = ThreadContext::with_memory_limit(1 << 20).memory_stats().unwrap().current;
+    assert_eq!(Some(Error::MemoryLimitExceeded), ThreadContext::try_with_memory_limit(size / 2).err());
+    assert_eq!(Some(Error::MemoryLimitExceeded), ThreadContext::builder().memory_limit(size / 2).build().err());
+
+    let c = ThreadContext::with_memory_limit(size);
+    assert_eq!(Some(Error::MemoryLimitExceeded), c.try_clone().err());
+    assert_eq!(Some(Error::MemoryLimitExceeded), crate::Profile::try_new_srgb_context(&c).err());
+    assert_eq!(Some(Error::MemoryLimitExceeded), crate::Profile::try_new_xyz_context(&c).err());
+    assert_eq!(Some(Error::MemoryLimitExceeded), crate::Profile::try_new_null_context(&c).err());
+}
+
+#[test]
+fn error_handler() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let mut c = ThreadContext::new();
+    let errors2 = errors.clone();
+    c.set_error_handler(move |code, msg| errors2.lock().unwrap().push((code, msg.to_string())));
+    assert_eq!(Err(Error::ObjectCreationError), crate::Profile::new_icc_context(&c, b"not a profile").map(drop));
+    assert_eq!(ErrorCode::Read, errors.lock().unwrap()[0].0);
+    assert!(c.last_error().is_none());
+
+    c.set_error_capture(true);
+    let c2 = c.clone();
+    match crate::Profile::new_icc_context(&c2, &[0; 200]).map(drop) {
+        Err(Error::CorruptProfile(details)) => {
+            assert_eq!(ErrorCode::BadSignature, details.code);
+            assert_eq!("Profile::new_icc_context", details.function);
+        },
+        other => panic!("{other:?}"),
+    }
+    assert_eq!(2, errors.lock().unwrap().len());
+    assert!(c2.last_error().is_none());
+
+    assert!(c.last_error().is_none());
+    assert!(matches!(crate::Profile::new_icc_context(&c, b"not a profile either"), Err(Error::CorruptProfile(_))));
+
+    let gray = crate::Profile::new_gray_context(&c, &crate::CIExyY { x: 0.3457, y: 0.3585, Y: 1. }, &crate::ToneCurve::new(2.2)).unwrap();
+    let srgb = crate::Profile::new_srgb_context(&c);
+    let err = crate::Transform::<[u8; 3], [u8; 3], _>::new_context(&c, &gray, crate::PixelFormat::RGB_8, &srgb, crate::PixelFormat::RGB_8, Intent::Perceptual).unwrap_err();
+    assert!(matches!(err, Error::UnsupportedColorSpace(_)), "{err}");
+    assert_eq!("Transform::new_flags_context", err.details().unwrap().function);
+    c.set_error_capture(false);
+    assert!(matches!(crate::Profile::new_icc_context(&c, b"not a profile either"), Err(Error::ObjectCreationError)));
+
+    c.set_error_capture(true);
+    c.set_error_logging_function(None);
+    assert!(matches!(crate::Profile::new_icc_context(&c, b"not a profile either"), Err(Error::ObjectCreationError)));
+}
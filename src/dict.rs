@@ -0,0 +1,185 @@
+use crate::ffi::wchar_t;
+use crate::{ffi, Error, LCMSResult, MLURef};
+use foreign_types::{foreign_type, ForeignTypeRef};
+use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+foreign_type! {
+    /// Key/value metadata stored in `dict` type tags, such as `TagSignature::MetaTag`.
+    ///
+    /// Entries can have localized display names and values. Owned version of `DictRef`.
+    pub unsafe type Dict {
+        type CType = c_void;
+        fn drop = ffi::cmsDictFree;
+        fn clone = ffi::cmsDictDup;
+    }
+}
+
+impl Dict {
+    /// Allocates an empty dictionary
+    #[track_caller]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::try_new().unwrap()
+    }
+
+    /// Like `new()`, but returns an error instead of panicking when the allocation fails
+    pub fn try_new() -> LCMSResult<Self> {
+        unsafe { Error::if_null(ffi::cmsDictAlloc(ptr::null_mut())) }
+    }
+}
+
+impl Default for Dict {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An entry of `Dict`
+#[derive(Debug, Clone)]
+pub struct DictEntry<'a> {
+    pub name: String,
+    pub value: String,
+    pub display_name: Option<&'a MLURef>,
+    pub display_value: Option<&'a MLURef>,
+}
+
+impl DictRef {
+    /// Appends an entry. Names should be unique, but LCMS doesn't check that.
+    pub fn add(&mut self, name: &str, value: &str) -> bool {
+        self.add_localized(name, value, None, None)
+    }
+
+    /// Appends an entry with optional localized names to display instead of the name and value.
+    pub fn add_localized(&mut self, name: &str, value: &str, display_name: Option<&MLURef>, display_value: Option<&MLURef>) -> bool {
+        let name = to_wide(name);
+        let value = to_wide(value);
+        let mlu_ptr = |mlu: Option<&MLURef>| mlu.map_or(ptr::null(), |m| m.as_ptr().cast_const());
+        unsafe {
+            ffi::cmsDictAddEntry(self.as_ptr(), name.as_ptr(), value.as_ptr(), mlu_ptr(display_name), mlu_ptr(display_value)) != 0
+        }
+    }
+
+    /// Value of the first entry with the given name
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(self.iter().find(|e| e.name == name)?.value)
+    }
+
+    /// Entries in the order they were added
+    #[must_use]
+    pub fn iter(&self) -> DictIter<'_> {
+        DictIter {
+            next: unsafe { ffi::cmsDictGetEntryList(self.as_ptr()) },
+            _dict: PhantomData,
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// Iterator over entries of `DictRef`
+pub struct DictIter<'a> {
+    next: *const ffi::DICTentry,
+    _dict: PhantomData<&'a DictRef>,
+}
+
+impl<'a> Iterator for DictIter<'a> {
+    type Item = DictEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            let e = self.next.as_ref()?;
+            self.next = ffi::cmsDictNextEntry(e);
+            let mlu = |m: *mut ffi::MLU| if m.is_null() { None } else { Some(MLURef::from_ptr(m)) };
+            Some(DictEntry {
+                name: from_wide(e.Name).unwrap_or_default(),
+                value: from_wide(e.Value).unwrap_or_default(),
+                display_name: mlu(e.DisplayName),
+                display_value: mlu(e.DisplayValue),
+            })
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a DictRef {
+    type Item = DictEntry<'a>;
+    type IntoIter = DictIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// `wchar_t` is UTF-16 on Windows, and UTF-32 elsewhere
+fn to_wide(s: &str) -> Vec<wchar_t> {
+    if mem::size_of::<wchar_t>() == 2 {
+        s.encode_utf16().map(|c| c as wchar_t).chain([0]).collect()
+    } else {
+        s.chars().map(|c| c as wchar_t).chain([0]).collect()
+    }
+}
+
+unsafe fn from_wide(s: *const wchar_t) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    let len = (0..).take_while(|&i| *s.add(i) != 0).count();
+    let chars = std::slice::from_raw_parts(s, len);
+    Some(if mem::size_of::<wchar_t>() == 2 {
+        decode_utf16(chars.iter().map(|&c| c as u16)).map(|r| r.unwrap_or(REPLACEMENT_CHARACTER)).collect()
+    } else {
+        chars.iter().map(|&c| char::from_u32(c as u32).unwrap_or(REPLACEMENT_CHARACTER)).collect()
+    })
+}
+
+impl fmt::Debug for DictRef {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter().map(|e| (e.name, e.value))).finish()
+    }
+}
+
+impl fmt::Debug for Dict {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[test]
+fn dict() {
+    use crate::{Locale, Profile, Tag, TagSignature, MLU};
+
+    let mut d = Dict::new();
+    assert!(d.is_empty());
+    assert!(d.add("tool", "rust-lcms2"));
+    assert!(d.add("license", "CC0 ✓"));
+    let mut name = MLU::new(1);
+    name.set_text("Quelle", Locale::new("de_DE"));
+    assert!(d.add_localized("source", "", Some(&name), None));
+
+    let mut p = Profile::new_srgb();
+    assert!(p.write_tag(TagSignature::MetaTag, Tag::Dict(&d)));
+    drop(d);
+    let p = Profile::new_icc(&p.icc().unwrap()).unwrap();
+    let Tag::Dict(d) = p.read_tag(TagSignature::MetaTag) else { panic!() };
+
+    let d = d.to_owned();
+    drop(p);
+    assert_eq!(Some("rust-lcms2"), d.get("tool").as_deref());
+    assert_eq!(Some("CC0 ✓"), d.get("license").as_deref());
+    let entries: Vec<_> = d.iter().collect();
+    assert_eq!(3, entries.len());
+    assert_eq!("source", entries[2].name);
+    assert_eq!("", entries[2].value);
+    assert_eq!("Quelle", entries[2].display_name.unwrap().text(Locale::new("de_DE")).unwrap());
+    assert!(entries[0].display_name.is_none());
+}
@@ -1,15 +1,116 @@
+use crate::{PixelFormatError, ProfileRejection};
 use foreign_types::ForeignType;
 use std::error::Error as StdError;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Errors with `ErrorDetails` are reported by LCMS, and are only available if the `ThreadContext` has error capture enabled (see `ThreadContext::set_error_capture()`).
+/// Otherwise LCMS failures are reported as `ObjectCreationError`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
     ObjectCreationError,
     MissingData,
     InvalidString,
+    /// ICC data is truncated, has invalid signature, or malformed tags
+    CorruptProfile(ErrorDetails),
+    /// The profile's color space can't be used for this operation, or doesn't match the pixel format
+    UnsupportedColorSpace(ErrorDetails),
+    /// The intent, tag type, profile version or other feature isn't supported by LCMS
+    Unsupported(ErrorDetails),
+    /// Reading or writing a file or stream has failed
+    Io(ErrorDetails),
+    /// An argument is out of range or missing
+    BadParameter(ErrorDetails),
+    /// Any other error reported by LCMS
+    Lcms(ErrorDetails),
+    /// The `ThreadContext` has been created with a memory limit, and it has been reached
+    MemoryLimitExceeded,
+    /// The ICC data exceeds limits set in `ProfileLoadOptions`
+    ProfileRejected(ProfileRejection),
+    /// The pixel type or length of a slice doesn't match the `PixelFormat` of a `Transform`
+    PixelFormatMismatch(PixelFormatError),
+}
+
+/// Error code and message that LCMS has passed to the error logging function
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ErrorDetails {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Name of the function that has failed, e.g. `Profile::new_icc_context`. Empty if not known.
+    pub function: &'static str,
+}
+
+/// Kind of error reported by LCMS (`cmsERROR_*` constants)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    Undefined,
+    File,
+    Range,
+    Internal,
+    Null,
+    Read,
+    Seek,
+    Write,
+    UnknownExtension,
+    ColorspaceCheck,
+    AlreadyDefined,
+    BadSignature,
+    CorruptionDetected,
+    NotSuitable,
+    /// A code not known to this version of the crate
+    Other(u32),
+}
+
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => Self::Undefined,
+            1 => Self::File,
+            2 => Self::Range,
+            3 => Self::Internal,
+            4 => Self::Null,
+            5 => Self::Read,
+            6 => Self::Seek,
+            7 => Self::Write,
+            8 => Self::UnknownExtension,
+            9 => Self::ColorspaceCheck,
+            10 => Self::AlreadyDefined,
+            11 => Self::BadSignature,
+            12 => Self::CorruptionDetected,
+            13 => Self::NotSuitable,
+            other => Self::Other(other),
+        }
+    }
 }
 
 impl Error {
+    /// Categorizes the error based on LCMS's error code. The message is kept as-is.
+    #[must_use]
+    pub fn from_details(details: ErrorDetails) -> Self {
+        use ErrorCode::*;
+        match details.code {
+            ColorspaceCheck => Error::UnsupportedColorSpace(details),
+            // reads fail when the ICC data is truncated
+            BadSignature | CorruptionDetected | Read => Error::CorruptProfile(details),
+            File | Seek | Write => Error::Io(details),
+            Range | Null => Error::BadParameter(details),
+            UnknownExtension | NotSuitable => Error::Unsupported(details),
+            _ => Error::Lcms(details),
+        }
+    }
+
+    /// Code, message and function name of errors reported by LCMS
+    #[must_use]
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            Error::ObjectCreationError | Error::MissingData | Error::InvalidString | Error::MemoryLimitExceeded |
+            Error::ProfileRejected(_) | Error::PixelFormatMismatch(_) => None,
+            Error::CorruptProfile(d) | Error::UnsupportedColorSpace(d) | Error::Unsupported(d) |
+            Error::Io(d) | Error::BadParameter(d) | Error::Lcms(d) => Some(d),
+        }
+    }
+
     #[inline]
     pub(crate) unsafe fn if_null<T>(handle: *mut <T as ForeignType>::CType) -> LCMSResult<T> where T: ForeignType {
         if !handle.is_null() {
@@ -26,13 +127,53 @@ pub type LCMSResult<T> = Result<T, Error>;
 impl fmt::Display for Error {
     #[cold]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(match *self {
-            Error::ObjectCreationError => "Could not create the object.\nThe reason is not known, but it's usually caused by wrong input parameters.",
-            Error::InvalidString => "String is not valid. Contains unsupported characters or is too long.",
-            Error::MissingData => "Requested data is empty or does not exist.",
-        })
+        let (desc, details) = match self {
+            Error::ObjectCreationError => ("Could not create the object.\nThe reason is not known, but it's usually caused by wrong input parameters. Use `ThreadContext::set_error_capture()` to get details.", None),
+            Error::InvalidString => ("String is not valid. Contains unsupported characters or is too long.", None),
+            Error::MissingData => ("Requested data is empty or does not exist.", None),
+            Error::MemoryLimitExceeded => ("Memory limit of the context has been exceeded.", None),
+            Error::ProfileRejected(_) => ("ICC profile rejected by load options", None),
+            Error::PixelFormatMismatch(_) => ("Pixel type doesn't match the pixel format", None),
+            Error::CorruptProfile(d) => ("Corrupt ICC profile", Some(d)),
+            Error::UnsupportedColorSpace(d) => ("Unsupported color space", Some(d)),
+            Error::Unsupported(d) => ("Unsupported feature", Some(d)),
+            Error::Io(d) => ("I/O error", Some(d)),
+            Error::BadParameter(d) => ("Bad parameter", Some(d)),
+            Error::Lcms(d) => ("LCMS error", Some(d)),
+        };
+        f.write_str(desc)?;
+        match self {
+            Error::ProfileRejected(reason) => write!(f, ": {reason}")?,
+            Error::PixelFormatMismatch(reason) => write!(f, ": {reason}")?,
+            _ => {},
+        }
+        if let Some(details) = details {
+            write!(f, ": {details}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ErrorDetails {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.function.is_empty() {
+            write!(f, "{}: ", self.function)?;
+        }
+        write!(f, "{} ({:?})", self.message, self.code)
     }
 }
 
 impl StdError for Error {
 }
+
+#[test]
+fn categorized_by_code() {
+    let details = |code| ErrorDetails { code, message: "Unsupported intent '5'".into(), function: "" };
+    assert!(matches!(Error::from_details(details(ErrorCode::UnknownExtension)), Error::Unsupported(_)));
+    assert!(matches!(Error::from_details(details(ErrorCode::Range)), Error::BadParameter(_)));
+    match Error::from_details(details(ErrorCode::Internal)) {
+        Error::Lcms(d) => assert_eq!("Unsupported intent '5'", d.message),
+        other => panic!("{other:?}"),
+    }
+}
@@ -1,12 +1,20 @@
 use crate::ffi;
+use std::os::raw::c_void;
+
+pub(crate) type SamplerFn<V> = unsafe extern "C" fn(input: *const V, output: *mut V, cargo: *mut c_void) -> i32;
 
 pub trait FloatOrU16: Sized + Copy {
+    const IS_FLOAT: bool;
     unsafe fn eval_tone_curve(self, handle: *const ffi::ToneCurve) -> Self;
     unsafe fn eval_pipeline(handle: *const ffi::Pipeline, input: &[Self], out: &mut [Self]);
     unsafe fn stage_alloc_clut(contextid: ffi::Context, ngridpoints: u32, inputchan: u32, outputchan: u32, table: *const Self) -> *mut ffi::Stage;
+    unsafe fn stage_sample_clut(mpe: *mut ffi::Stage, sampler: SamplerFn<Self>, cargo: *mut c_void, flags: u32) -> i32;
+    unsafe fn slice_space(ninputs: u32, clut_points: *const u32, sampler: SamplerFn<Self>, cargo: *mut c_void) -> i32;
 }
 
 impl FloatOrU16 for f32 {
+    const IS_FLOAT: bool = true;
+
     #[inline]
     unsafe fn eval_tone_curve(self, handle: *const ffi::ToneCurve) -> Self {
         ffi::cmsEvalToneCurveFloat(handle, self)
@@ -21,9 +29,21 @@ impl FloatOrU16 for f32 {
     unsafe fn stage_alloc_clut(contextid: ffi::Context, ngridpoints: u32, inputchan: u32, outputchan: u32, table: *const Self) -> *mut ffi::Stage {
         ffi::cmsStageAllocCLutFloat(contextid, ngridpoints, inputchan, outputchan, table)
     }
+
+    #[inline]
+    unsafe fn stage_sample_clut(mpe: *mut ffi::Stage, sampler: SamplerFn<Self>, cargo: *mut c_void, flags: u32) -> i32 {
+        ffi::cmsStageSampleCLutFloat(mpe, sampler, cargo, flags)
+    }
+
+    #[inline]
+    unsafe fn slice_space(ninputs: u32, clut_points: *const u32, sampler: SamplerFn<Self>, cargo: *mut c_void) -> i32 {
+        ffi::cmsSliceSpaceFloat(ninputs, clut_points, sampler, cargo)
+    }
 }
 
 impl FloatOrU16 for u16 {
+    const IS_FLOAT: bool = false;
+
     #[inline]
     unsafe fn eval_tone_curve(self, handle: *const ffi::ToneCurve) -> Self {
         ffi::cmsEvalToneCurve16(handle, self)
@@ -38,4 +58,14 @@ impl FloatOrU16 for u16 {
     unsafe fn stage_alloc_clut(contextid: ffi::Context, ngridpoints: u32, inputchan: u32, outputchan: u32, table: *const Self) -> *mut ffi::Stage {
         ffi::cmsStageAllocCLut16bit(contextid, ngridpoints, inputchan, outputchan, table)
     }
+
+    #[inline]
+    unsafe fn stage_sample_clut(mpe: *mut ffi::Stage, sampler: SamplerFn<Self>, cargo: *mut c_void, flags: u32) -> i32 {
+        ffi::cmsStageSampleCLut16bit(mpe, sampler, cargo, flags)
+    }
+
+    #[inline]
+    unsafe fn slice_space(ninputs: u32, clut_points: *const u32, sampler: SamplerFn<Self>, cargo: *mut c_void) -> i32 {
+        ffi::cmsSliceSpace16(ninputs, clut_points, sampler, cargo)
+    }
 }
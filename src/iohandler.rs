@@ -0,0 +1,156 @@
+use crate::ffi;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+/// Layout of `cmsIOHANDLER` from `lcms2_plugin.h`, followed by Rust-only fields that LCMS doesn't know about.
+///
+/// LCMS reads tags lazily, so the handler is owned by the profile, and freed in `close`.
+#[repr(C)]
+pub(crate) struct IoHandler<S> {
+    stream: *mut c_void,
+    context_id: ffi::Context,
+    used_space: u32,
+    reported_size: u32,
+    physical_file: [c_char; 256],
+    read: unsafe extern "C" fn(*mut Self, *mut c_void, u32, u32) -> u32,
+    seek: unsafe extern "C" fn(*mut Self, u32) -> ffi::Bool,
+    close: unsafe extern "C" fn(*mut Self) -> ffi::Bool,
+    tell: unsafe extern "C" fn(*mut Self) -> u32,
+    write: unsafe extern "C" fn(*mut Self, u32, *const c_void) -> ffi::Bool,
+
+    stream_obj: S,
+    /// Position of the profile's start in the stream
+    base: u64,
+    error: Option<io::Error>,
+    /// After the first error all calls fail, so that it can be reported by `open_state`.
+    /// Lazy reads of tags after opening don't report errors, so each of them gets a fresh start.
+    sticky_errors: bool,
+    /// Set while opening, so that the error and ownership can be reported back if LCMS closes the handler
+    open_state: *mut OpenState,
+}
+
+#[derive(Default)]
+pub(crate) struct OpenState {
+    pub closed: bool,
+    pub error: Option<io::Error>,
+}
+
+impl<S: Seek> IoHandler<S> {
+    fn new(context: ffi::Context, mut stream: S, reported_size: u32) -> io::Result<Self> {
+        let base = stream.stream_position()?;
+        Ok(Self {
+            stream: ptr::null_mut(),
+            context_id: context,
+            used_space: 0,
+            reported_size,
+            physical_file: [0; 256],
+            read: io_read_fail::<S>,
+            seek: io_seek::<S>,
+            close: io_close_noop::<S>,
+            tell: io_tell::<S>,
+            write: io_write_fail::<S>,
+            stream_obj: stream,
+            base,
+            error: None,
+            sticky_errors: true,
+            open_state: ptr::null_mut(),
+        })
+    }
+
+    /// Runs the callback, and keeps the first I/O error (if errors are sticky). Panics are caught, because they can't unwind through C.
+    unsafe fn with<R>(this: *mut Self, fail: R, f: impl FnOnce(&mut Self) -> io::Result<R>) -> R {
+        let this = &mut *this;
+        if this.error.is_some() {
+            return fail;
+        }
+        let err = match panic::catch_unwind(AssertUnwindSafe(|| f(this))) {
+            Ok(Ok(res)) => return res,
+            Ok(Err(err)) => err,
+            Err(_) => io::Error::new(io::ErrorKind::Other, "panic in I/O"),
+        };
+        if this.sticky_errors {
+            this.error = Some(err);
+        }
+        fail
+    }
+
+    fn position(&mut self) -> io::Result<u32> {
+        let pos = self.stream_obj.stream_position()? - self.base;
+        u32::try_from(pos).map_err(|_| io::ErrorKind::InvalidData.into())
+    }
+}
+
+impl<R: Read + Seek> IoHandler<R> {
+    /// The handler for reading a profile. The stream's current position is the start of the profile.
+    pub fn new_reader(context: ffi::Context, mut reader: R) -> io::Result<Box<Self>> {
+        let start = reader.stream_position()?;
+        let len = reader.seek(SeekFrom::End(0))? - start;
+        reader.seek(SeekFrom::Start(start))?;
+        let len = u32::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream is too large"))?;
+        let mut handler = Box::new(Self::new(context, reader, len)?);
+        handler.read = io_read::<R>;
+        handler.close = io_close_owned::<R>;
+        Ok(handler)
+    }
+
+    /// Gives the handler to LCMS. If LCMS doesn't close the handler on failure, the caller must free it.
+    pub fn into_raw(self: Box<Self>, open_state: &mut OpenState) -> *mut ffi::IOHANDLER {
+        let handler = Box::into_raw(self);
+        unsafe { (*handler).open_state = open_state };
+        handler.cast()
+    }
+
+    /// After opening, the handler is owned by the profile, and a failed read of one tag doesn't affect others
+    pub unsafe fn opened(handler: *mut ffi::IOHANDLER) {
+        let handler = &mut *handler.cast::<Self>();
+        handler.open_state = ptr::null_mut();
+        handler.sticky_errors = false;
+    }
+
+    pub unsafe fn free(handler: *mut ffi::IOHANDLER) {
+        drop(Box::from_raw(handler.cast::<Self>()));
+    }
+}
+
+unsafe extern "C" fn io_read<R: Read + Seek>(this: *mut IoHandler<R>, buffer: *mut c_void, size: u32, count: u32) -> u32 {
+    IoHandler::with(this, 0, |this| {
+        let len = size as usize * count as usize;
+        this.stream_obj.read_exact(slice::from_raw_parts_mut(buffer.cast::<u8>(), len))?;
+        Ok(count)
+    })
+}
+
+unsafe extern "C" fn io_seek<S: Seek>(this: *mut IoHandler<S>, offset: u32) -> ffi::Bool {
+    IoHandler::with(this, 0, |this| {
+        this.stream_obj.seek(SeekFrom::Start(this.base + u64::from(offset)))?;
+        Ok(1)
+    })
+}
+
+unsafe extern "C" fn io_tell<S: Seek>(this: *mut IoHandler<S>) -> u32 {
+    IoHandler::with(this, 0, |this| this.position())
+}
+
+unsafe extern "C" fn io_read_fail<S>(_: *mut IoHandler<S>, _: *mut c_void, _: u32, _: u32) -> u32 {
+    0
+}
+
+unsafe extern "C" fn io_write_fail<S>(_: *mut IoHandler<S>, _: u32, _: *const c_void) -> ffi::Bool {
+    0
+}
+
+unsafe extern "C" fn io_close_noop<S>(_: *mut IoHandler<S>) -> ffi::Bool {
+    1
+}
+
+unsafe extern "C" fn io_close_owned<S>(this: *mut IoHandler<S>) -> ffi::Bool {
+    let mut this = Box::from_raw(this);
+    if let Some(state) = this.open_state.as_mut() {
+        state.closed = true;
+        state.error = this.error.take();
+    }
+    1
+}
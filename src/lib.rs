@@ -23,19 +23,29 @@ use lcms2_sys as ffi;
 
 mod ciecam;
 mod context;
+mod dict;
 mod error;
 mod eval;
 mod ext;
 mod flags;
+mod iohandler;
+mod loadoptions;
 mod locale;
+mod memory;
 mod mlu;
 mod namedcolorlist;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod pipeline;
+mod pixel;
 mod profile;
+mod profilesequence;
+mod sampler;
 mod stage;
 mod tag;
 mod tonecurve;
 mod transform;
+mod transformbuilder;
 use std::marker::PhantomData;
 
 /// `Transform` requires pixel types to implement these traits.
@@ -44,18 +54,29 @@ use std::marker::PhantomData;
 pub use bytemuck::{Pod, Zeroable};
 
 pub use crate::ciecam::*;
-pub use crate::context::{GlobalContext, ThreadContext};
+pub use crate::context::{GlobalContext, ThreadContext, ThreadContextBuilder};
+pub use crate::dict::*;
 pub use crate::error::*;
 pub use crate::ext::*;
 pub use crate::flags::*;
+pub use crate::loadoptions::*;
 pub use crate::locale::*;
+pub use crate::memory::MemoryStats;
 pub use crate::mlu::*;
 pub use crate::namedcolorlist::*;
+#[cfg(feature = "parallel")]
+pub use crate::parallel::*;
 pub use crate::pipeline::*;
+pub use crate::pixel::*;
+#[cfg(feature = "derive")]
+pub use lcms2_derive::LcmsPixel;
 pub use crate::profile::*;
+pub use crate::profilesequence::*;
 pub use crate::stage::*;
+pub use crate::tag::{ICCDataBuf, TagValue};
 pub use crate::tonecurve::*;
 pub use crate::transform::*;
+pub use crate::transformbuilder::*;
 
 pub use crate::ffi::CIELab;
 /// Part of [`CIExyYTRIPLE`]
@@ -67,10 +88,13 @@ pub use crate::ffi::JCh;
 pub use crate::ffi::CIEXYZ;
 
 pub use crate::ffi::ColorSpaceSignature;
+/// For [`Profile::creation_date`]
+pub use crate::ffi::DateTimeNumber;
 pub use crate::ffi::InfoType;
 pub use crate::ffi::Intent;
 pub use crate::ffi::PixelFormat;
 pub use crate::ffi::ProfileClassSignature;
+pub use crate::ffi::StageLoc;
 pub use crate::ffi::TagSignature;
 pub use crate::ffi::VideoSignalType;
 pub use crate::ffi::ViewingConditions;
@@ -99,6 +123,8 @@ pub enum Tag<'a> {
     VcgtCurves([&'a ToneCurveRef; 3]),
     VideoSignal(&'a ffi::VideoSignalType),
     MHC2(&'a ffi::MHC2Type),
+    /// Key/value metadata
+    Dict(&'a DictRef),
     /// Unknown format or missing data
     None,
 }
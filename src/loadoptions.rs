@@ -0,0 +1,231 @@
+use crate::{Error, LCMSResult};
+use std::fmt;
+
+/// Limits for loading ICC profiles from untrusted sources, e.g. embedded in user-uploaded images.
+///
+/// The profile's header and tag table are checked in Rust before the data is passed to LCMS,
+/// so that suspicious profiles are rejected the same way regardless of the version of LCMS.
+///
+/// See `Profile::new_icc_with_options()`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProfileLoadOptions {
+    /// Maximum length of the ICC data in bytes
+    pub max_profile_size: usize,
+    /// Maximum number of entries in the tag table
+    pub max_tag_count: u32,
+    /// Maximum size of a single tag in bytes
+    pub max_tag_size: u32,
+    /// Maximum number of nodes in a single CLUT (the product of grid points of all input channels)
+    pub max_clut_grid_points: u64,
+    /// Reject tags whose data overlaps with other tags. Tags sharing exactly the same data are allowed.
+    pub reject_overlapping_tags: bool,
+}
+
+impl Default for ProfileLoadOptions {
+    /// Generous limits that allow all reasonable profiles
+    fn default() -> Self {
+        Self {
+            max_profile_size: 16 << 20,
+            max_tag_count: 100,
+            max_tag_size: 16 << 20,
+            max_clut_grid_points: 1 << 24,
+            reject_overlapping_tags: true,
+        }
+    }
+}
+
+/// Reason why `ProfileLoadOptions` have rejected a profile. Tags are identified by their signatures.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ProfileRejection {
+    /// The data is shorter than the header and the tag table
+    Truncated,
+    ProfileTooLarge { size: usize, max: usize },
+    TooManyTags { count: u32, max: u32 },
+    TagTooLarge { tag: u32, size: u32, max: u32 },
+    /// Tag's data is outside of the profile
+    TagOutOfBounds { tag: u32 },
+    OverlappingTags { tag: u32, other: u32 },
+    TooManyGridPoints { tag: u32, grid_points: u64, max: u64 },
+}
+
+const HEADER_SIZE: usize = 128;
+
+impl ProfileLoadOptions {
+    /// Checks the ICC data without parsing it with LCMS.
+    ///
+    /// Only the structure of the profile is checked, so data that passes may still fail to load.
+    pub fn validate(&self, data: &[u8]) -> LCMSResult<()> {
+        self.check(data).map_err(Error::ProfileRejected)
+    }
+
+    fn check(&self, data: &[u8]) -> Result<(), ProfileRejection> {
+        if data.len() > self.max_profile_size {
+            return Err(ProfileRejection::ProfileTooLarge { size: data.len(), max: self.max_profile_size });
+        }
+        // LCMS uses the smaller of the two sizes
+        let declared_size = read_u32(data, 0).ok_or(ProfileRejection::Truncated)? as usize;
+        let profile = &data[..data.len().min(declared_size)];
+
+        let count = read_u32(profile, HEADER_SIZE).ok_or(ProfileRejection::Truncated)?;
+        if count > self.max_tag_count {
+            return Err(ProfileRejection::TooManyTags { count, max: self.max_tag_count });
+        }
+
+        let mut tags = Vec::with_capacity(count as usize);
+        for i in 0..count as usize {
+            let entry = HEADER_SIZE + 4 + i * 12;
+            let (Some(tag), Some(offset), Some(size)) = (read_u32(profile, entry), read_u32(profile, entry + 4), read_u32(profile, entry + 8)) else {
+                return Err(ProfileRejection::Truncated);
+            };
+            if size > self.max_tag_size {
+                return Err(ProfileRejection::TagTooLarge { tag, size, max: self.max_tag_size });
+            }
+            let tag_data = profile.get(offset as usize..(offset as usize).saturating_add(size as usize))
+                .ok_or(ProfileRejection::TagOutOfBounds { tag })?;
+            let grid_points = clut_grid_points(tag_data);
+            if grid_points > self.max_clut_grid_points {
+                return Err(ProfileRejection::TooManyGridPoints { tag, grid_points, max: self.max_clut_grid_points });
+            }
+            tags.push((offset, size, tag));
+        }
+
+        if self.reject_overlapping_tags {
+            tags.sort_unstable();
+            for pair in tags.windows(2) {
+                let ((offset, size, tag), (next_offset, next_size, other)) = (pair[0], pair[1]);
+                let shared = offset == next_offset && size == next_size;
+                if !shared && u64::from(offset) + u64::from(size) > u64::from(next_offset) {
+                    return Err(ProfileRejection::OverlappingTags { tag, other });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The largest CLUT in a lut8, lut16, lutAtoB, lutBtoA or multiProcessElements tag. 0 if there isn't any, or it's malformed (LCMS will reject it).
+fn clut_grid_points(tag: &[u8]) -> u64 {
+    let Some(sig) = tag.get(..4) else { return 0 };
+    let in_channels = tag.get(8).copied().unwrap_or(0) as usize;
+    match sig {
+        b"mft1" | b"mft2" => {
+            let points = tag.get(10).copied().unwrap_or(0);
+            u64::from(points).saturating_pow(in_channels as u32)
+        },
+        b"mAB " | b"mBA " => match read_u32(tag, 24) {
+            Some(offset) if offset != 0 => grid_product(tag, offset as usize, in_channels),
+            _ => 0,
+        },
+        b"mpet" => {
+            let elements = read_u32(tag, 12).unwrap_or(0) as usize;
+            // elements can change the number of channels, so each CLUT has its own input count
+            (0..elements).map_while(|i| read_u32(tag, 16 + i * 8))
+                .map(|offset| offset as usize)
+                .filter(|&offset| tag.get(offset..offset.saturating_add(4)) == Some(b"clut"))
+                .filter_map(|offset| Some(grid_product(tag, offset + 12, read_u16(tag, offset + 8)? as usize)))
+                .max().unwrap_or(0)
+        },
+        _ => 0,
+    }
+}
+
+/// Grid points are stored as an array of 16 bytes, one per input channel
+fn grid_product(tag: &[u8], offset: usize, in_channels: usize) -> u64 {
+    match tag.get(offset..offset + in_channels.min(16)) {
+        Some(points) => points.iter().fold(1u64, |acc, &p| acc.saturating_mul(u64::from(p))),
+        None => 0,
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+impl fmt::Display for ProfileRejection {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sig = |tag: &u32| String::from_utf8_lossy(&tag.to_be_bytes()).into_owned();
+        match self {
+            Self::Truncated => f.write_str("the header or tag table is truncated"),
+            Self::ProfileTooLarge { size, max } => write!(f, "profile is {size} bytes, max is {max}"),
+            Self::TooManyTags { count, max } => write!(f, "profile has {count} tags, max is {max}"),
+            Self::TagTooLarge { tag, size, max } => write!(f, "tag '{}' is {size} bytes, max is {max}", sig(tag)),
+            Self::TagOutOfBounds { tag } => write!(f, "tag '{}' is outside of the profile", sig(tag)),
+            Self::OverlappingTags { tag, other } => write!(f, "tags '{}' and '{}' overlap", sig(tag), sig(other)),
+            Self::TooManyGridPoints { tag, grid_points, max } => write!(f, "CLUT in tag '{}' has {grid_points} grid points, max is {max}", sig(tag)),
+        }
+    }
+}
+
+#[test]
+fn load_options() {
+    use crate::Profile;
+
+    let icc = Profile::new_srgb().icc().unwrap();
+    let options = ProfileLoadOptions::default();
+    options.validate(&icc).unwrap();
+    Profile::new_icc_with_options(&icc, &options).unwrap();
+
+    let small = ProfileLoadOptions { max_profile_size: 100, ..options.clone() };
+    assert_eq!(Err(Error::ProfileRejected(ProfileRejection::ProfileTooLarge { size: icc.len(), max: 100 })), small.validate(&icc));
+    assert_eq!(Err(Error::ProfileRejected(ProfileRejection::Truncated)), options.validate(&icc[..130]));
+    let few_tags = ProfileLoadOptions { max_tag_count: 2, ..options.clone() };
+    assert!(matches!(Profile::new_icc_with_options(&icc, &few_tags), Err(Error::ProfileRejected(ProfileRejection::TooManyTags { max: 2, .. }))));
+    let small_tags = ProfileLoadOptions { max_tag_size: 8, ..options.clone() };
+    assert!(matches!(small_tags.validate(&icc), Err(Error::ProfileRejected(ProfileRejection::TagTooLarge { max: 8, .. }))));
+
+    // make the first tag overlap the second one
+    let mut overlapping = icc.clone();
+    let second_offset = read_u32(&icc, HEADER_SIZE + 4 + 12 + 4).unwrap();
+    let first_offset = read_u32(&icc, HEADER_SIZE + 4 + 4).unwrap();
+    overlapping[HEADER_SIZE + 4 + 8..][..4].copy_from_slice(&(second_offset - first_offset + 4).to_be_bytes());
+    assert!(matches!(options.validate(&overlapping), Err(Error::ProfileRejected(ProfileRejection::OverlappingTags { .. }))));
+    ProfileLoadOptions { reject_overlapping_tags: false, ..options.clone() }.validate(&overlapping).unwrap();
+
+    let mut out_of_bounds = icc.clone();
+    out_of_bounds[HEADER_SIZE + 4 + 4..][..4].copy_from_slice(&(icc.len() as u32).to_be_bytes());
+    assert!(matches!(options.validate(&out_of_bounds), Err(Error::ProfileRejected(ProfileRejection::TagOutOfBounds { .. }))));
+
+    let mut lut = Vec::new();
+    lut.extend_from_slice(b"mft2\0\0\0\0");
+    lut.extend_from_slice(&[4, 3, 33, 0]);
+    assert_eq!(33 * 33 * 33 * 33, clut_grid_points(&lut));
+    let mut lut = vec![0; 48];
+    lut[..4].copy_from_slice(b"mAB ");
+    lut[8] = 3;
+    lut[24..28].copy_from_slice(&32u32.to_be_bytes());
+    lut[32..35].copy_from_slice(&[255, 255, 255]);
+    assert_eq!(255 * 255 * 255, clut_grid_points(&lut));
+
+    // 3 inputs expanded by a matrix to 6 inputs of the CLUT
+    let mut mpet = Vec::new();
+    mpet.extend_from_slice(b"mpet\0\0\0\0");
+    mpet.extend_from_slice(&[0, 3, 0, 3]);
+    mpet.extend_from_slice(&2u32.to_be_bytes());
+    mpet.extend_from_slice(&[0, 0, 0, 32, 0, 0, 0, 12]);
+    mpet.extend_from_slice(&[0, 0, 0, 44, 0, 0, 0, 28]);
+    mpet.extend_from_slice(b"matf\0\0\0\0");
+    mpet.extend_from_slice(&[0, 3, 0, 6]);
+    mpet.extend_from_slice(b"clut\0\0\0\0");
+    mpet.extend_from_slice(&[0, 6, 0, 3]);
+    mpet.extend_from_slice(&[17; 6]);
+    mpet.extend_from_slice(&[0; 10]);
+    assert_eq!(17u64.pow(6), clut_grid_points(&mpet));
+    assert_eq!(0, clut_grid_points(&mpet[..45]));
+
+    let mut profile = vec![0; HEADER_SIZE];
+    profile.extend_from_slice(&1u32.to_be_bytes());
+    profile.extend_from_slice(b"D2B0");
+    profile.extend_from_slice(&(HEADER_SIZE as u32 + 16).to_be_bytes());
+    profile.extend_from_slice(&(mpet.len() as u32).to_be_bytes());
+    profile.extend_from_slice(&mpet);
+    let len = profile.len() as u32;
+    profile[..4].copy_from_slice(&len.to_be_bytes());
+    let limited = ProfileLoadOptions { max_clut_grid_points: 17 * 17 * 17 * 17, ..options };
+    assert!(matches!(limited.validate(&profile), Err(Error::ProfileRejected(ProfileRejection::TooManyGridPoints { grid_points, .. })) if grid_points == 17u64.pow(6)));
+}
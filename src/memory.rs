@@ -0,0 +1,132 @@
+use crate::context::memory_budget;
+use crate::ffi;
+// LCMS falls back to the default allocator for objects that outlive their context, so this must use the same `malloc`/`free`.
+use libc::{free, malloc, realloc};
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::Mutex;
+
+/// Memory usage of a `ThreadContext` created with `ThreadContext::with_memory_limit()`
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct MemoryStats {
+    /// Bytes currently allocated
+    pub current: usize,
+    /// The highest number of bytes allocated at once
+    pub peak: usize,
+    /// Number of successful allocations (including reallocations)
+    pub allocations: usize,
+    /// Number of allocations refused because they'd exceed the limit (or failed for other reasons)
+    pub failed: usize,
+}
+
+/// Shared by clones of the context
+pub(crate) struct MemoryBudget {
+    limit: usize,
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    stats: MemoryStats,
+    /// LCMS doesn't pass sizes to `free`
+    sizes: HashMap<usize, usize>,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, state: Mutex::new(BudgetState::default()) }
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).stats
+    }
+
+    unsafe fn realloc(&self, old: *mut c_void, size: usize) -> *mut c_void {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let old_size = if old.is_null() { 0 } else { state.sizes.get(&(old as usize)).copied().unwrap_or(0) };
+        if size == 0 || state.stats.current - old_size + size > self.limit {
+            state.stats.failed += 1;
+            return ptr::null_mut();
+        }
+        let new = if old.is_null() { malloc(size) } else { realloc(old, size) };
+        if new.is_null() {
+            state.stats.failed += 1;
+            return ptr::null_mut();
+        }
+        if !old.is_null() {
+            state.sizes.remove(&(old as usize));
+        }
+        state.sizes.insert(new as usize, size);
+        let stats = &mut state.stats;
+        stats.current = stats.current - old_size + size;
+        stats.peak = stats.peak.max(stats.current);
+        stats.allocations += 1;
+        new
+    }
+
+    unsafe fn free(&self, ptr: *mut c_void) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(size) = state.sizes.remove(&(ptr as usize)) {
+            state.stats.current -= size;
+        }
+        free(ptr);
+    }
+}
+
+/// Layout of `cmsPluginMemHandler` from `lcms2_plugin.h`
+#[repr(C)]
+pub(crate) struct MemHandlerPlugin {
+    magic: u32,
+    expected_version: u32,
+    plugin_type: u32,
+    next: *mut c_void,
+    malloc: unsafe extern "C" fn(ffi::Context, u32) -> *mut c_void,
+    free: unsafe extern "C" fn(ffi::Context, *mut c_void),
+    realloc: unsafe extern "C" fn(ffi::Context, *mut c_void, u32) -> *mut c_void,
+    malloc_zero: Option<unsafe extern "C" fn(ffi::Context, u32) -> *mut c_void>,
+    calloc: Option<unsafe extern "C" fn(ffi::Context, u32, u32) -> *mut c_void>,
+    dup: Option<unsafe extern "C" fn(ffi::Context, *const c_void, u32) -> *mut c_void>,
+}
+
+impl MemHandlerPlugin {
+    /// Allocator that enforces `MemoryBudget` of the context
+    pub fn new() -> Self {
+        Self {
+            magic: 0x61637070,           // 'acpp'
+            expected_version: 2060,
+            plugin_type: 0x6D656D48,     // 'memH'
+            next: ptr::null_mut(),
+            malloc: budget_malloc,
+            free: budget_free,
+            realloc: budget_realloc,
+            // defaults use malloc
+            malloc_zero: None,
+            calloc: None,
+            dup: None,
+        }
+    }
+}
+
+unsafe extern "C" fn budget_malloc(context: ffi::Context, size: u32) -> *mut c_void {
+    budget_realloc(context, ptr::null_mut(), size)
+}
+
+unsafe extern "C" fn budget_realloc(context: ffi::Context, ptr: *mut c_void, size: u32) -> *mut c_void {
+    match memory_budget(context) {
+        Some(budget) => budget.realloc(ptr, size as usize),
+        // The context itself is allocated before it has user data
+        None if ptr.is_null() => if size == 0 { ptr::null_mut() } else { malloc(size as usize) },
+        None => realloc(ptr, size as usize),
+    }
+}
+
+unsafe extern "C" fn budget_free(context: ffi::Context, ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    match memory_budget(context) {
+        Some(budget) => budget.free(ptr),
+        None => free(ptr),
+    }
+}
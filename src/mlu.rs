@@ -13,6 +13,7 @@ foreign_type! {
     pub unsafe type MLU {
         type CType = ffi::MLU;
         fn drop = ffi::cmsMLUfree;
+        fn clone = ffi::cmsMLUdup;
     }
 }
 
@@ -181,6 +182,13 @@ impl fmt::Debug for MLURef {
     }
 }
 
+impl fmt::Debug for MLU {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 #[test]
 fn mlu() {
     let _ = MLU::new(0);
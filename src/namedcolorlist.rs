@@ -20,6 +20,7 @@ foreign_type! {
     pub unsafe type NamedColorList {
         type CType = ffi::NAMEDCOLORLIST;
         fn drop = ffi::cmsFreeNamedColorList;
+        fn clone = ffi::cmsDupNamedColorList;
     }
 }
 
@@ -111,6 +112,13 @@ impl<'a> fmt::Debug for NamedColorListRef {
     }
 }
 
+impl fmt::Debug for NamedColorList {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 #[test]
 fn named() {
     let mut n = NamedColorList::new(10, 3, "hello", "world").unwrap();
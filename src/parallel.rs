@@ -0,0 +1,178 @@
+use crate::context::Context;
+use crate::transform::is_u8;
+use crate::*;
+use std::panic;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+impl<InputPixelFormat, OutputPixelFormat, Ctx> Transform<InputPixelFormat, OutputPixelFormat, Ctx, DisallowCache>
+where
+    InputPixelFormat: Copy + Pod + Sync,
+    OutputPixelFormat: Copy + Pod + Send,
+    Ctx: Context + Send,
+{
+    /// Same as `transform_pixels()`, but splits the slices into chunks and transforms them on multiple threads.
+    ///
+    /// Chunks have at least `min_chunk_pixels` pixels (unless the image is smaller than that), so that small images aren't split into pieces
+    /// that take longer to spawn a thread for than to transform. The number of threads is limited by `std::thread::available_parallelism()`.
+    ///
+    /// Requires the `parallel` feature. The transform must be created with `Flags::NO_CACHE`. For cached transforms see [`TransformPool`].
+    #[track_caller]
+    pub fn transform_pixels_parallel(&self, src: &[InputPixelFormat], dst: &mut [OutputPixelFormat], min_chunk_pixels: usize) {
+        let pixels = self.pixel_count(src.len(), dst.len());
+        let src_elements = elements_per_pixel::<InputPixelFormat>(self.input_pixel_format());
+        let dst_elements = elements_per_pixel::<OutputPixelFormat>(self.output_pixel_format());
+        for_each_chunk(src, src_elements, dst, dst_elements, pixels, min_chunk_pixels, |src, dst| self.transform_pixels(src, dst));
+    }
+}
+
+/// Keeps a separate `Transform` for every thread, so that transforms that use the 1-pixel cache can be used in parallel.
+///
+/// The transforms are created from the same profiles, flags and context settings (plugins, adaptation state, alarm codes, error handler, memory limit),
+/// so they give exactly the same results. Each one has its own clone of the `ThreadContext`.
+/// They're all created upfront, one for every thread in `std::thread::available_parallelism()`, and reused by subsequent calls.
+///
+/// Requires the `parallel` feature. If you can afford to disable the cache, [`Transform::transform_pixels_parallel`] is simpler.
+pub struct TransformPool<InputPixelFormat, OutputPixelFormat> {
+    in_format: PixelFormat,
+    out_format: PixelFormat,
+    idle: Mutex<Vec<PooledTransform<InputPixelFormat, OutputPixelFormat>>>,
+    returned: Condvar,
+}
+
+struct PooledTransform<InputPixelFormat, OutputPixelFormat> {
+    // must be dropped before the context
+    transform: Transform<InputPixelFormat, OutputPixelFormat, ThreadContext>,
+    _context: ThreadContext,
+}
+
+/// Returns the transform to the pool even if the thread panics
+struct Lease<'pool, InputPixelFormat, OutputPixelFormat> {
+    pool: &'pool TransformPool<InputPixelFormat, OutputPixelFormat>,
+    pooled: Option<PooledTransform<InputPixelFormat, OutputPixelFormat>>,
+}
+
+impl<InputPixelFormat: Copy + Pod + Sync, OutputPixelFormat: Copy + Pod + Send> TransformPool<InputPixelFormat, OutputPixelFormat> {
+    /// Takes the same arguments as `Transform::new_flags_context()`.
+    ///
+    /// Profiles must use a `ThreadContext` (see `Profile::new_icc_context()`), because LCMS's global context can't be copied.
+    pub fn new(context: &ThreadContext, input: &Profile<ThreadContext>, in_format: PixelFormat,
+               output: &Profile<ThreadContext>, out_format: PixelFormat,
+               intent: Intent, flags: Flags) -> LCMSResult<Self> {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let idle = (0..threads).map(|_| {
+            let context = context.clone();
+            let transform = Transform::new_flags_context(&context, input, in_format, output, out_format, intent, flags)?;
+            Ok(PooledTransform { transform, _context: context })
+        }).collect::<LCMSResult<_>>()?;
+        Ok(Self {
+            in_format,
+            out_format,
+            idle: Mutex::new(idle),
+            returned: Condvar::new(),
+        })
+    }
+
+    /// Waits if other calls are using all of the transforms
+    fn take(&self) -> Lease<'_, InputPixelFormat, OutputPixelFormat> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(pooled) = idle.pop() {
+                return Lease { pool: self, pooled: Some(pooled) };
+            }
+            idle = self.returned.wait(idle).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Same as `Transform::transform_pixels()`, but splits the slices into chunks of at least `min_chunk_pixels` and transforms them on multiple threads.
+    #[track_caller]
+    pub fn transform_pixels(&self, src: &[InputPixelFormat], dst: &mut [OutputPixelFormat], min_chunk_pixels: usize) {
+        let pixels = self.take().transform().pixel_count(src.len(), dst.len());
+        let src_elements = elements_per_pixel::<InputPixelFormat>(self.in_format);
+        let dst_elements = elements_per_pixel::<OutputPixelFormat>(self.out_format);
+        for_each_chunk(src, src_elements, dst, dst_elements, pixels, min_chunk_pixels, |src, dst| {
+            self.take().transform().transform_pixels(src, dst);
+        });
+    }
+}
+
+impl<InputPixelFormat, OutputPixelFormat> Lease<'_, InputPixelFormat, OutputPixelFormat> {
+    fn transform(&self) -> &Transform<InputPixelFormat, OutputPixelFormat, ThreadContext> {
+        &self.pooled.as_ref().unwrap().transform
+    }
+}
+
+impl<InputPixelFormat, OutputPixelFormat> Drop for Lease<'_, InputPixelFormat, OutputPixelFormat> {
+    fn drop(&mut self) {
+        if let Some(pooled) = self.pooled.take() {
+            self.pool.idle.lock().unwrap_or_else(|e| e.into_inner()).push(pooled);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+fn elements_per_pixel<P: 'static>(format: PixelFormat) -> usize {
+    if is_u8::<P>() { format.bytes_per_pixel().max(1) } else { 1 }
+}
+
+/// Calls `f` on matching chunks of `src` and `dst`, each chunk in its own thread
+fn for_each_chunk<I: Sync, O: Send, R: Send>(src: &[I], src_elements: usize, dst: &mut [O], dst_elements: usize,
+                                             pixels: usize, min_chunk_pixels: usize,
+                                             f: impl Fn(&[I], &mut [O]) -> R + Sync) -> Vec<R> {
+    let src = &src[..pixels * src_elements];
+    let dst = &mut dst[..pixels * dst_elements];
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_pixels = ((pixels + threads - 1) / threads).max(min_chunk_pixels).clamp(1, u32::MAX as usize);
+    if chunk_pixels >= pixels {
+        return vec![f(src, dst)];
+    }
+
+    let f = &f;
+    thread::scope(|s| {
+        let threads: Vec<_> = src.chunks(chunk_pixels * src_elements)
+            .zip(dst.chunks_mut(chunk_pixels * dst_elements))
+            .map(|(src, dst)| s.spawn(move || f(src, dst)))
+            .collect();
+        threads.into_iter()
+            .map(|t| t.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+#[test]
+fn parallel() {
+    let ctx = ThreadContext::new();
+    let srgb = Profile::new_srgb_context(&ctx);
+    let gray = Profile::new_gray_context(&ctx, &CIExyY { x: 0.3457, y: 0.3585, Y: 1. }, &ToneCurve::new(1.8)).unwrap();
+    let src: Vec<[u8; 3]> = (0..10_000u32).map(|i| [i as u8, (i >> 8) as u8, (i * 7) as u8]).collect();
+
+    let t = Transform::new_flags_context(&ctx, &srgb, PixelFormat::RGB_8, &gray, PixelFormat::GRAY_8, Intent::Perceptual, Flags::NO_CACHE).unwrap();
+    let mut expected = vec![0u8; src.len()];
+    t.transform_pixels(&src, &mut expected);
+
+    let mut dst = vec![0u8; src.len()];
+    t.transform_pixels_parallel(&src, &mut dst, 100);
+    assert_eq!(expected, dst);
+
+    let t = Transform::<u8, u8, _, _>::new_flags_context(&ctx, &srgb, PixelFormat::RGB_8, &gray, PixelFormat::GRAY_8, Intent::Perceptual, Flags::NO_CACHE).unwrap();
+    let mut dst = vec![0u8; src.len() - 1];
+    t.transform_pixels_parallel(bytemuck::cast_slice(&src), &mut dst, 1);
+    assert_eq!(expected[..dst.len()], dst[..]);
+
+    // the pool must give the same results as a single transform, including settings of the context
+    let mut ctx = ThreadContext::with_memory_limit(10 << 20);
+    ctx.set_adaptation_state(0.5);
+    let srgb = Profile::new_srgb_context(&ctx);
+    let gray = Profile::new_gray_context(&ctx, &CIExyY { x: 0.3457, y: 0.3585, Y: 1. }, &ToneCurve::new(1.8)).unwrap();
+    let t = Transform::new_flags_context(&ctx, &srgb, PixelFormat::RGB_8, &gray, PixelFormat::GRAY_8, Intent::AbsoluteColorimetric, Flags::default()).unwrap();
+    t.transform_pixels(&src, &mut expected);
+
+    let pool = TransformPool::new(&ctx, &srgb, PixelFormat::RGB_8, &gray, PixelFormat::GRAY_8, Intent::AbsoluteColorimetric, Flags::default()).unwrap();
+    let mut dst = vec![0u8; src.len()];
+    pool.transform_pixels(&src, &mut dst, 1000);
+    assert_eq!(expected, dst);
+    let mut dst = vec![0u8; 5];
+    pool.transform_pixels(&src, &mut dst, 1);
+    assert_eq!(expected[..5], dst[..]);
+}
@@ -1,7 +1,7 @@
 use crate::eval::FloatOrU16;
-use crate::stage::{StageRef, StagesIter};
-use crate::{ffi, Error, LCMSResult};
-use foreign_types::{foreign_type, ForeignTypeRef};
+use crate::stage::{Stage, StageRef, StagesIter};
+use crate::{ffi, Error, LCMSResult, StageLoc};
+use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
 use std::fmt;
 use std::ptr;
 
@@ -38,6 +38,60 @@ impl PipelineRef {
         unsafe { ffi::cmsPipelineCat((self as *mut Self).cast(), append.as_ptr()) != 0 }
     }
 
+    /// Adds the stage at the beginning or the end of the pipeline, taking ownership of it.
+    ///
+    /// Channels of the stage must connect with the neighboring stage (or the pipeline's channels if it's empty).
+    /// If they don't, the stage is returned back as an error.
+    pub fn insert_stage(&mut self, loc: StageLoc, stage: Stage) -> Result<(), Stage> {
+        let fits = match loc {
+            StageLoc::AT_BEGIN => stage.output_channels() == self.input_channels(),
+            StageLoc::AT_END => stage.input_channels() == self.output_channels(),
+        };
+        if !fits {
+            return Err(stage);
+        }
+        unsafe {
+            let res = ffi::cmsPipelineInsertStage((self as *mut Self).cast(), loc, stage.as_ptr());
+            // The stage is in the pipeline, even if it failed
+            std::mem::forget(stage);
+            debug_assert!(res != 0);
+        }
+        Ok(())
+    }
+
+    /// Removes the first or the last stage from the pipeline, and gives it back.
+    pub fn unlink_stage(&mut self, loc: StageLoc) -> Option<Stage> {
+        unsafe {
+            let mut stage = ptr::null_mut();
+            ffi::cmsPipelineUnlinkStage((self as *mut Self).cast(), loc, &mut stage);
+            if stage.is_null() {
+                None
+            } else {
+                Some(Stage::from_ptr(stage))
+            }
+        }
+    }
+
+    /// Swaps the first or the last stage for another one with the same number of channels, and returns the old stage.
+    ///
+    /// If the pipeline is empty or the channels differ, the new stage is returned back as an error.
+    pub fn replace_stage(&mut self, loc: StageLoc, stage: Stage) -> Result<Stage, Stage> {
+        let old = match loc {
+            StageLoc::AT_BEGIN => self.first_stage(),
+            StageLoc::AT_END => self.last_stage(),
+        };
+        match old {
+            Some(old) if old.input_channels() == stage.input_channels() && old.output_channels() == stage.output_channels() => {},
+            _ => return Err(stage),
+        }
+        let Some(old) = self.unlink_stage(loc) else {
+            return Err(stage);
+        };
+        // Channels are the same as the unlinked stage's, so it can't fail
+        self.insert_stage(loc, stage).unwrap();
+        Ok(old)
+    }
+
     #[must_use]
     pub fn stage_count(&self) -> usize {
         unsafe { ffi::cmsPipelineStageCount(self.as_ptr()) as usize }
@@ -95,6 +149,53 @@ impl PipelineRef {
         }
     }
 
+    /// Finds input values for which the pipeline outputs `target`, using Newton-Raphson iteration.
+    ///
+    /// Works only with pipelines that have 3 output channels and 3 or 4 input channels.
+    /// For 4 input channels the `target` has a 4th value, which is fixed as the 4th input channel (e.g. black in CMYK).
+    ///
+    /// The search starts from `hint` (3 input values), or 0.3 in all channels if there's no hint.
+    /// Returns `None` if the iteration failed, or `target` doesn't have a value for each input channel, or `hint` has fewer than 3 values.
+    /// The result is the closest one found, which may not be an exact match.
+    #[must_use]
+    pub fn eval_reverse(&self, target: &[f32], hint: Option<&[f32]>) -> Option<Vec<f32>> {
+        let inputs = self.input_channels();
+        if self.output_channels() != 3 || (inputs != 3 && inputs != 4) || target.len() != inputs {
+            return None;
+        }
+        let mut target_tmp = [0f32; 4];
+        target_tmp[..target.len()].copy_from_slice(target);
+        let mut hint_tmp = [0f32; 3];
+        if let Some(hint) = hint {
+            hint_tmp.copy_from_slice(hint.get(..3)?);
+        }
+        let mut result = vec![0f32; inputs];
+        let ok = unsafe {
+            ffi::cmsPipelineEvalReverseFloat(target_tmp.as_mut_ptr(), result.as_mut_ptr(),
+                if hint.is_some() { hint_tmp.as_mut_ptr() } else { ptr::null_mut() },
+                self.as_ptr())
+        };
+        if ok != 0 { Some(result) } else { None }
+    }
+
+    /// Same as `eval_reverse`, but tries starting from each of the `hints`, and picks the result that is closest to the `target`.
+    ///
+    /// Returns input values and their residual error (Euclidean distance between the pipeline's output and the target).
+    #[must_use]
+    pub fn eval_reverse_multi(&self, target: &[f32], hints: &[[f32; 3]]) -> Option<(Vec<f32>, f32)> {
+        let mut output = [0f32; 3];
+        let mut best: Option<(Vec<f32>, f32)> = None;
+        for hint in hints {
+            let Some(input) = self.eval_reverse(target, Some(hint)) else { continue };
+            self.eval(&input, &mut output);
+            let error = output.iter().zip(target).map(|(o, t)| (o - t) * (o - t)).sum::<f32>().sqrt();
+            if best.as_ref().map_or(true, |(_, best_error)| error < *best_error) {
+                best = Some((input, error));
+            }
+        }
+        best
+    }
+
     // You must ensure that input and output have length sufficient for channels
     #[inline]
     pub unsafe fn eval_unchecked<Value: FloatOrU16>(&self, input: &[Value], output: &mut [Value]) {
@@ -109,6 +210,13 @@ impl fmt::Debug for PipelineRef {
     }
 }
 
+impl fmt::Debug for Pipeline {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 #[test]
 fn pipeline() {
     let p = Pipeline::new(123, 12);
@@ -119,3 +227,60 @@ fn pipeline() {
     assert_eq!(4, p.input_channels());
     assert_eq!(3, p.output_channels());
 }
+
+#[test]
+fn edit_stages() {
+    let mut p = Pipeline::new(3, 3).unwrap();
+    assert!(p.unlink_stage(StageLoc::AT_END).is_none());
+    assert!(p.insert_stage(StageLoc::AT_END, Stage::new_identity(4)).is_err());
+
+    let to_gray = Stage::new_matrix(&[0.5, 0.25, 0.25], 1, 3, None).unwrap();
+    assert_eq!((3, 1), (to_gray.input_channels(), to_gray.output_channels()));
+    p.insert_stage(StageLoc::AT_END, to_gray).unwrap();
+    p.insert_stage(StageLoc::AT_BEGIN, Stage::new_identity(3)).unwrap();
+    assert_eq!(2, p.stage_count());
+    assert_eq!(1, p.output_channels());
+    assert!(p.insert_stage(StageLoc::AT_END, Stage::new_identity(3)).is_err());
+    p.insert_stage(StageLoc::AT_END, Stage::new_identity(1)).unwrap();
+
+    let mut out = [0f32; 1];
+    p.eval(&[1., 0.5, 0.], &mut out);
+    assert!((out[0] - 0.625).abs() < 0.0001);
+
+    let old = p.replace_stage(StageLoc::AT_BEGIN, Stage::new_matrix(&[0., 0., 1., 0., 1., 0., 1., 0., 0.], 3, 3, None).unwrap()).unwrap();
+    assert_eq!(ffi::StageSignature::IdentityElemType, old.stage_type());
+    assert!(p.replace_stage(StageLoc::AT_BEGIN, Stage::new_identity(1)).is_err());
+    p.eval(&[1., 0.5, 0.], &mut out);
+    assert!((out[0] - 0.375).abs() < 0.0001);
+
+    let last = p.unlink_stage(StageLoc::AT_END).unwrap();
+    assert_eq!(1, last.input_channels());
+    assert_eq!(2, p.stage_count());
+    assert!(p.unlink_stage(StageLoc::AT_BEGIN).is_some());
+    assert!(p.unlink_stage(StageLoc::AT_BEGIN).is_some());
+    assert!(p.unlink_stage(StageLoc::AT_BEGIN).is_none());
+}
+
+#[test]
+fn eval_reverse() {
+    let mut p = Pipeline::new(3, 3).unwrap();
+    let m = Stage::new_matrix(&[0.5, 0.25, 0., 0., 1., 0.125, 0.25, 0., 0.5], 3, 3, None).unwrap();
+    p.insert_stage(StageLoc::AT_END, m).unwrap();
+
+    let mut target = [0f32; 3];
+    p.eval(&[0.2, 0.4, 0.6], &mut target);
+    let found = p.eval_reverse(&target, None).unwrap();
+    assert!(found.iter().zip([0.2, 0.4, 0.6]).all(|(a, b)| (a - b).abs() < 0.001), "{found:?}");
+    let found = p.eval_reverse(&target, Some(&[0.9, 0.9, 0.9])).unwrap();
+    assert!((found[2] - 0.6).abs() < 0.001);
+
+    let (found, error) = p.eval_reverse_multi(&target, &[[0., 0., 0.], [1., 1., 1.]]).unwrap();
+    assert!((found[1] - 0.4).abs() < 0.001);
+    assert!(error < 0.001);
+    assert!(p.eval_reverse_multi(&target, &[]).is_none());
+    assert!(p.eval_reverse(&target[..2], None).is_none());
+    assert!(p.eval_reverse(&target, Some(&[0.5])).is_none());
+
+    p.insert_stage(StageLoc::AT_END, Stage::new_matrix(&[1., 1., 1.], 1, 3, None).unwrap()).unwrap();
+    assert!(p.eval_reverse(&[0.5, 0.5, 0.5], None).is_none());
+}
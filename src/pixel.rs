@@ -0,0 +1,140 @@
+use crate::{Pod, PixelFormat};
+use std::marker::PhantomData;
+use std::mem;
+
+/// A pixel type that knows its `PixelFormat`, so that `Transform::new_typed()` doesn't need format arguments.
+///
+/// It's implemented for `u8`/`u16`/`f32`/`f64` samples of gray, and arrays of 2, 3 and 4 of them (gray+alpha, RGB and RGBA).
+/// With the `rgb` feature it's also implemented for pixel types from the [`rgb` crate](https://lib.rs/crates/rgb).
+///
+/// For your own `#[repr(C)]` structs, use the `impl_lcms_pixel!` macro, which also checks the size of the type at compile time,
+/// or `#[derive(LcmsPixel)]` with `#[lcms2(format = RGB_8)]` attribute (requires the `derive` feature).
+pub trait LcmsPixel: Copy + Pod {
+    /// Packed (non-planar) format with the same number of bytes per pixel as the type
+    const FORMAT: PixelFormat;
+}
+
+/// Number of bytes per pixel of a packed format, or 0 for planar formats. Used by `impl_lcms_pixel!`.
+#[doc(hidden)]
+#[must_use]
+pub const fn packed_pixel_size(format: PixelFormat) -> usize {
+    let planar = (format.0 >> 12) & 1;
+    let bytes = format.0 & 7;
+    let channels = (format.0 >> 3) & 15;
+    let extra = (format.0 >> 7) & 7;
+    if planar != 0 {
+        return 0;
+    }
+    // 8 overflows the field
+    let bytes = if bytes != 0 { bytes } else { 8 };
+    (bytes * (channels + extra)) as usize
+}
+
+/// Fails to compile when a generic function is used with an `LcmsPixel` that has a wrong `FORMAT`
+pub(crate) struct CheckPixel<P>(PhantomData<P>);
+
+impl<P: LcmsPixel> CheckPixel<P> {
+    pub(crate) const SIZE_MATCHES: () = assert!(mem::size_of::<P>() == packed_pixel_size(P::FORMAT), "Size of the LcmsPixel type doesn't match its FORMAT");
+}
+
+/// Implements `LcmsPixel` for `#[repr(C)]` pixel structs. The struct must also implement `Pod`.
+///
+/// ```rust
+/// use lcms2::*;
+///
+/// #[derive(Copy, Clone)]
+/// #[repr(C)]
+/// struct Cmyk { c: u8, m: u8, y: u8, k: u8 }
+/// unsafe impl Pod for Cmyk {}
+/// unsafe impl Zeroable for Cmyk {}
+///
+/// impl_lcms_pixel!(Cmyk => PixelFormat::CMYK_8);
+/// ```
+///
+/// The size of the struct is checked at compile time:
+///
+/// ```rust,compile_fail
+/// # use lcms2::*;
+/// # #[derive(Copy, Clone)]
+/// # #[repr(C)]
+/// # struct Rgb { r: u8, g: u8, b: u8 }
+/// # unsafe impl Pod for Rgb {}
+/// # unsafe impl Zeroable for Rgb {}
+/// impl_lcms_pixel!(Rgb => PixelFormat::RGB_16);
+/// ```
+#[macro_export]
+macro_rules! impl_lcms_pixel {
+    ($($ty:ty => $format:expr),+ $(,)?) => {$(
+        impl $crate::LcmsPixel for $ty {
+            const FORMAT: $crate::PixelFormat = $format;
+        }
+        const _: () = assert!(
+            ::std::mem::size_of::<$ty>() == $crate::packed_pixel_size(<$ty as $crate::LcmsPixel>::FORMAT),
+            "Size of the type doesn't match bytes per pixel of the packed PixelFormat"
+        );
+    )+};
+}
+
+impl_lcms_pixel! {
+    u8 => PixelFormat::GRAY_8,
+    u16 => PixelFormat::GRAY_16,
+    f32 => PixelFormat::GRAY_FLT,
+    f64 => PixelFormat::GRAY_DBL,
+    [u8; 1] => PixelFormat::GRAY_8,
+    [u16; 1] => PixelFormat::GRAY_16,
+    [f32; 1] => PixelFormat::GRAY_FLT,
+    [f64; 1] => PixelFormat::GRAY_DBL,
+    [u8; 2] => PixelFormat::GRAYA_8,
+    [u16; 2] => PixelFormat::GRAYA_16,
+    [u8; 3] => PixelFormat::RGB_8,
+    [u16; 3] => PixelFormat::RGB_16,
+    [f32; 3] => PixelFormat::RGB_FLT,
+    [f64; 3] => PixelFormat::RGB_DBL,
+    [u8; 4] => PixelFormat::RGBA_8,
+    [u16; 4] => PixelFormat::RGBA_16,
+    [f32; 4] => PixelFormat::RGBA_FLT,
+}
+
+#[cfg(feature = "rgb")]
+mod rgb_impls {
+    use crate::PixelFormat;
+    use rgb::alt::{ABGR, ARGB, BGR, BGRA, Gray, GrayAlpha};
+    use rgb::{RGB, RGBA};
+
+    impl_lcms_pixel! {
+        RGB<u8> => PixelFormat::RGB_8,
+        RGB<u16> => PixelFormat::RGB_16,
+        RGB<f32> => PixelFormat::RGB_FLT,
+        RGB<f64> => PixelFormat::RGB_DBL,
+        RGBA<u8> => PixelFormat::RGBA_8,
+        RGBA<u16> => PixelFormat::RGBA_16,
+        RGBA<f32> => PixelFormat::RGBA_FLT,
+        BGR<u8> => PixelFormat::BGR_8,
+        BGR<u16> => PixelFormat::BGR_16,
+        BGR<f32> => PixelFormat::BGR_FLT,
+        BGR<f64> => PixelFormat::BGR_DBL,
+        BGRA<u8> => PixelFormat::BGRA_8,
+        BGRA<u16> => PixelFormat::BGRA_16,
+        BGRA<f32> => PixelFormat::BGRA_FLT,
+        ARGB<u8> => PixelFormat::ARGB_8,
+        ARGB<u16> => PixelFormat::ARGB_16,
+        ARGB<f32> => PixelFormat::ARGB_FLT,
+        ABGR<u8> => PixelFormat::ABGR_8,
+        ABGR<u16> => PixelFormat::ABGR_16,
+        Gray<u8> => PixelFormat::GRAY_8,
+        Gray<u16> => PixelFormat::GRAY_16,
+        Gray<f32> => PixelFormat::GRAY_FLT,
+        Gray<f64> => PixelFormat::GRAY_DBL,
+        GrayAlpha<u8> => PixelFormat::GRAYA_8,
+        GrayAlpha<u16> => PixelFormat::GRAYA_16,
+    }
+}
+
+#[test]
+fn pixel_sizes() {
+    assert_eq!(3, packed_pixel_size(PixelFormat::RGB_8));
+    assert_eq!(8, packed_pixel_size(PixelFormat::RGBA_16));
+    assert_eq!(24, packed_pixel_size(PixelFormat::Lab_DBL));
+    assert_eq!(0, packed_pixel_size(PixelFormat::RGB_8_PLANAR));
+    assert_eq!(PixelFormat::RGBA_FLT, <[f32; 4] as LcmsPixel>::FORMAT);
+}
@@ -1,11 +1,12 @@
-use crate::context::Context;
+use crate::context::{capture_errors, Context};
+use crate::iohandler::{IoHandler, OpenState};
 use crate::*;
 use foreign_types::ForeignTypeRef;
 use std::default::Default;
 use std::fmt;
 use std::fs::File;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Seek, Write};
 use std::mem::MaybeUninit;
 use std::os::raw::c_void;
 use std::path::Path;
@@ -30,12 +31,26 @@ impl Profile<GlobalContext> {
         Self::new_icc_context(GlobalContext::new(), data)
     }
 
+    /// Parse ICC profile from untrusted data, after checking it against the limits
+    #[inline]
+    pub fn new_icc_with_options(data: &[u8], options: &ProfileLoadOptions) -> LCMSResult<Self> {
+        Self::new_icc_context_with_options(GlobalContext::new(), data, options)
+    }
+
     /// Load ICC profile file from disk
     #[inline]
     pub fn new_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         Self::new_file_context(GlobalContext::new(), path)
     }
 
+    /// Parse ICC profile from a stream, starting at its current position.
+    ///
+    /// Tags are read lazily, so the profile keeps the reader until it's dropped.
+    #[inline]
+    pub fn from_reader<R: Read + Seek + Send + 'static>(reader: R) -> io::Result<Self> {
+        Self::from_reader_context(GlobalContext::new(), reader)
+    }
+
     /// Create an ICC virtual profile for sRGB space. sRGB is a standard RGB color space created cooperatively by HP and Microsoft in 1996 for use on monitors, printers, and the Internet.
     #[inline]
     #[must_use]
@@ -132,6 +147,15 @@ impl<Ctx: Context> Profile<Ctx> {
         }
     }
 
+    /// Write ICC file to a stream, e.g. a file, socket or hasher.
+    ///
+    /// The profile is serialized in memory first, like in `icc()`, because LCMS needs to seek back to fill in offsets of tags.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let data = self.icc().map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        writer.write_all(&data)?;
+        writer.flush()
+    }
+
     /// Gets the device class signature from profile header.
     #[inline]
     #[must_use]
@@ -416,6 +440,21 @@ impl<Ctx: Context> Profile<Ctx> {
         unsafe { ffi::cmsWriteTag(self.handle, sig, tag.data_for_signature(sig).cast()) != 0 }
     }
 
+    /// Same as `read_tag()`, but copies the value, so that it can be kept after the profile is modified or dropped.
+    ///
+    /// Returns `None` if the tag is missing or its type isn't supported by `TagValue`.
+    #[inline]
+    #[must_use]
+    pub fn read_tag_owned(&self, sig: TagSignature) -> Option<TagValue> {
+        TagValue::from_tag(&self.read_tag(sig))
+    }
+
+    /// Same as `write_tag()`. LCMS copies the value, so it doesn't need to outlive this call.
+    #[inline]
+    pub fn write_tag_value(&mut self, sig: TagSignature, value: &TagValue) -> bool {
+        self.write_tag(sig, value.as_tag())
+    }
+
     #[inline]
     pub fn remove_tag(&mut self, sig: TagSignature) -> bool {
         unsafe { ffi::cmsWriteTag(self.handle, sig, std::ptr::null()) != 0 }
@@ -426,6 +465,65 @@ impl<Ctx: Context> Profile<Ctx> {
         unsafe { ffi::cmsLinkTag(self.handle, sig, dst) != 0 }
     }
 
+    /// Signature of the tag that `sig` is linked to (shares data with), if any.
+    ///
+    /// Signatures are raw `u32`, so that private tags unknown to `TagSignature` can be used too, e.g. `u32::from_be_bytes(*b"meta")` or `TagSignature::MetaTag as u32`.
+    #[inline]
+    #[must_use]
+    pub fn tag_linked_to(&self, sig: u32) -> Option<u32> {
+        let linked = unsafe { cmsTagLinkedTo(self.handle, sig) };
+        if linked != 0 { Some(linked) } else { None }
+    }
+
+    /// Bytes of the tag as stored in the ICC file, including its type signature. Works for any tag, including private ones not known to LCMS.
+    ///
+    /// `sig` is a raw tag signature, e.g. `u32::from_be_bytes(*b"meta")`.
+    #[must_use]
+    pub fn read_raw_tag(&self, sig: u32) -> Option<Vec<u8>> {
+        unsafe {
+            let len = cmsReadRawTag(self.handle, sig, ptr::null_mut(), 0);
+            if len == 0 {
+                return None;
+            }
+            let mut data = vec![0u8; len as usize];
+            let len = cmsReadRawTag(self.handle, sig, data.as_mut_ptr().cast(), len);
+            if len == 0 {
+                return None;
+            }
+            data.truncate(len as usize);
+            Some(data)
+        }
+    }
+
+    /// Writes the tag's bytes as-is. The data must include the tag's type signature and reserved bytes, as in the ICC file.
+    ///
+    /// LCMS won't be able to read the tag with `read_tag()` until the profile is saved and opened again.
+    #[inline]
+    pub fn write_raw_tag(&mut self, sig: u32, data: &[u8]) -> bool {
+        let Ok(len) = data.len().try_into() else { return false };
+        unsafe { cmsWriteRawTag(self.handle, sig, data.as_ptr().cast(), len) != 0 }
+    }
+
+    /// Descriptions of profiles that have been combined into this device link, from `ProfileSequenceDescTag`.
+    ///
+    /// Profile IDs and descriptions are taken from `ProfileSequenceIdTag` if it's present, as LCMS does.
+    #[must_use]
+    pub fn profile_sequence(&self) -> Option<ProfileSequence> {
+        let Tag::SEQ(desc) = self.read_tag(TagSignature::ProfileSequenceDescTag) else { return None };
+        let mut seq = ProfileSequenceRef::from_ffi(desc).to_owned();
+        if let Tag::SEQ(ids) = self.read_tag(TagSignature::ProfileSequenceIdTag) {
+            seq.merge_ids(ProfileSequenceRef::from_ffi(ids));
+        }
+        Some(seq)
+    }
+
+    /// Writes `ProfileSequenceDescTag`, and for v4 profiles also `ProfileSequenceIdTag`
+    pub fn set_profile_sequence(&mut self, seq: &ProfileSequenceRef) -> bool {
+        let seq = unsafe { &*seq.as_ptr() };
+        self.write_tag(TagSignature::ProfileSequenceDescTag, Tag::SEQ(seq)) &&
+            (self.version() < 4. || self.write_tag(TagSignature::ProfileSequenceIdTag, Tag::SEQ(seq)))
+    }
+
     /// Retrieves the Profile ID stored in the profile header.
     #[inline]
     #[must_use]
@@ -438,6 +536,63 @@ impl<Ctx: Context> Profile<Ctx> {
         }
     }
 
+    /// Date and time of creation from the profile header (in UTC). `None` if the date isn't set.
+    #[must_use]
+    pub fn creation_date(&self) -> Option<DateTimeNumber> {
+        let mut tm = MaybeUninit::<libc::tm>::zeroed();
+        let tm = unsafe {
+            ffi::cmsGetHeaderCreationDateTime(self.handle, tm.as_mut_ptr().cast());
+            tm.assume_init()
+        };
+        let year = u16::try_from(tm.tm_year + 1900).ok().filter(|&y| y > 0)?;
+        Some(DateTimeNumber {
+            year,
+            month: u16::try_from(tm.tm_mon + 1).ok().filter(|&m| m > 0)?,
+            day: tm.tm_mday.try_into().ok()?,
+            hours: tm.tm_hour.try_into().ok()?,
+            minutes: tm.tm_min.try_into().ok()?,
+            seconds: tm.tm_sec.try_into().ok()?,
+        })
+    }
+
+    /// Changes the creation date in the profile header.
+    ///
+    /// LCMS can't change the date of an open profile, so the profile is saved and opened again. Invalidates the Profile ID (see `set_default_profile_id()`).
+    pub fn set_creation_date(&mut self, date: &DateTimeNumber) -> bool {
+        let Ok(mut data) = self.icc() else { return false };
+        let fields = [date.year, date.month, date.day, date.hours, date.minutes, date.seconds];
+        for (dst, field) in data[24..36].chunks_exact_mut(2).zip(fields) {
+            dst.copy_from_slice(&field.to_be_bytes());
+        }
+        let handle = unsafe {
+            ffi::cmsOpenProfileFromMemTHR(ffi::cmsGetProfileContextID(self.handle), data.as_ptr().cast(), data.len() as u32)
+        };
+        if handle.is_null() {
+            return false;
+        }
+        unsafe { ffi::cmsCloseProfile(self.handle) };
+        self.handle = handle;
+        true
+    }
+
+    /// Checks whether the Profile ID in the header matches the MD5 checksum of the profile.
+    ///
+    /// The checksum is computed on a copy of the profile saved by LCMS, so profiles with tags that LCMS doesn't save the same way may fail the check.
+    /// Returns `false` if the profile has no Profile ID (it's all zeros).
+    #[must_use]
+    pub fn verify_profile_id(&self) -> bool {
+        let stored = self.profile_id();
+        if stored == ffi::ProfileID::default() {
+            return false;
+        }
+        let Ok(data) = self.icc() else { return false };
+        unsafe {
+            let copy = ffi::cmsOpenProfileFromMemTHR(ffi::cmsGetProfileContextID(self.handle), data.as_ptr().cast(), data.len() as u32);
+            let Ok(copy) = Profile::<Ctx>::new_handle(copy) else { return false };
+            ffi::cmsMD5computeID(copy.handle) != 0 && copy.profile_id() == stored
+        }
+    }
+
     /// Computes a MD5 checksum and stores it as Profile ID in the profile header.
     #[inline]
     pub fn set_default_profile_id(&mut self) {
@@ -466,9 +621,17 @@ impl<Ctx: Context> Profile<Ctx> {
         if data.is_empty() {
             return Err(Error::MissingData);
         }
-        Self::new_handle(unsafe {
-            ffi::cmsOpenProfileFromMemTHR(context.as_ref().as_ptr(), data.as_ptr().cast::<c_void>(), data.len() as u32)
-        })
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Profile::new_icc_context", || Self::new_handle(unsafe {
+            ffi::cmsOpenProfileFromMemTHR(context, data.as_ptr().cast::<c_void>(), data.len() as u32)
+        }))
+    }
+
+    /// Checks the data with `ProfileLoadOptions::validate()` before parsing it
+    #[inline]
+    pub fn new_icc_context_with_options(context: impl AsRef<Ctx>, data: &[u8], options: &ProfileLoadOptions) -> LCMSResult<Self> {
+        options.validate(data)?;
+        Self::new_icc_context(context, data)
     }
 
     #[inline]
@@ -478,9 +641,42 @@ impl<Ctx: Context> Profile<Ctx> {
         Self::new_icc_context(context, &buf).map_err(|_| io::ErrorKind::Other.into())
     }
 
+    /// Parse ICC profile from a stream, starting at its current position.
+    ///
+    /// Tags are read lazily, so the profile keeps the reader until it's dropped.
+    pub fn from_reader_context<R: Read + Seek + Send + 'static>(context: impl AsRef<Ctx>, reader: R) -> io::Result<Self> {
+        let context = context.as_ref().as_ptr();
+        let mut state = OpenState::default();
+        let io = IoHandler::new_reader(context, reader)?.into_raw(&mut state);
+        let res = capture_errors(context, "Profile::from_reader_context", || Self::new_handle(unsafe {
+            ffi::cmsOpenProfileFromIOhandler2THR(context, io, 0)
+        }));
+        match res {
+            Ok(profile) => {
+                unsafe { IoHandler::<R>::opened(io) };
+                Ok(profile)
+            },
+            Err(err) => {
+                if !state.closed {
+                    unsafe { IoHandler::<R>::free(io) };
+                }
+                Err(state.error.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidData, err)))
+            },
+        }
+    }
+
+    /// Panics if the profile can't be allocated. See `try_new_srgb_context()` for contexts with a memory limit.
     #[inline]
+    #[track_caller]
     pub fn new_srgb_context(context: impl AsRef<Ctx>) -> Self {
-        Self::new_handle(unsafe { ffi::cmsCreate_sRGBProfileTHR(context.as_ref().as_ptr()) }).unwrap()
+        Self::try_new_srgb_context(context).unwrap()
+    }
+
+    /// Like `new_srgb_context()`, but returns `Error::MemoryLimitExceeded` instead of panicking
+    #[inline]
+    pub fn try_new_srgb_context(context: impl AsRef<Ctx>) -> LCMSResult<Self> {
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Profile::new_srgb_context", || Self::new_handle(unsafe { ffi::cmsCreate_sRGBProfileTHR(context) }))
     }
 
     #[inline]
@@ -529,13 +725,14 @@ impl<Ctx: Context> Profile<Ctx> {
     pub fn new_bchsw_abstract_context(context: impl AsRef<Ctx>, lut_points: usize, bright: f64, contrast: f64, hue: f64, saturation: f64,
                                       temp_src_dst: Option<(u32, u32)>) -> LCMSResult<Self> {
         let (temp_src, temp_dest) = temp_src_dst.unwrap_or((0,0));
-        Self::new_handle(unsafe {
-            ffi::cmsCreateBCHSWabstractProfileTHR(context.as_ref().as_ptr(), lut_points as _, bright, contrast, hue, saturation, temp_src as _, temp_dest as _)
-        })
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Profile::new_bchsw_abstract_context", || Self::new_handle(unsafe {
+            ffi::cmsCreateBCHSWabstractProfileTHR(context, lut_points as _, bright, contrast, hue, saturation, temp_src as _, temp_dest as _)
+        }))
     }
 
     #[inline]
-    fn new_handle(handle: ffi::HPROFILE) -> LCMSResult<Self> {
+    pub(crate) fn new_handle(handle: ffi::HPROFILE) -> LCMSResult<Self> {
         if handle.is_null() {
             return Err(Error::ObjectCreationError);
         }
@@ -554,14 +751,30 @@ impl<Ctx: Context> Profile<Ctx> {
 
     /// Creates a XYZ  XYZ identity, marking it as v4 ICC profile.  `WhitePoint` used in Absolute colorimetric intent  is D50.
     #[inline]
+    #[track_caller]
     pub fn new_xyz_context(context: impl AsRef<Ctx>) -> Self {
-        Self::new_handle(unsafe { ffi::cmsCreateXYZProfileTHR(context.as_ref().as_ptr()) }).unwrap()
+        Self::try_new_xyz_context(context).unwrap()
+    }
+
+    /// Like `new_xyz_context()`, but returns `Error::MemoryLimitExceeded` instead of panicking
+    #[inline]
+    pub fn try_new_xyz_context(context: impl AsRef<Ctx>) -> LCMSResult<Self> {
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Profile::new_xyz_context", || Self::new_handle(unsafe { ffi::cmsCreateXYZProfileTHR(context) }))
     }
 
     /// Creates a fake NULL profile. This profile return 1 channel as always 0. Is useful only for gamut checking tricks.
     #[inline]
+    #[track_caller]
     pub fn new_null_context(context: impl AsRef<Ctx>) -> Self {
-        Self::new_handle(unsafe { ffi::cmsCreateNULLProfileTHR(context.as_ref().as_ptr()) }).unwrap()
+        Self::try_new_null_context(context).unwrap()
+    }
+
+    /// Like `new_null_context()`, but returns `Error::MemoryLimitExceeded` instead of panicking
+    #[inline]
+    pub fn try_new_null_context(context: impl AsRef<Ctx>) -> LCMSResult<Self> {
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Profile::new_null_context", || Self::new_handle(unsafe { ffi::cmsCreateNULLProfileTHR(context) }))
     }
 
     /// Creates a Lab  Lab identity, marking it as v2 ICC profile.
@@ -578,6 +791,214 @@ impl<Ctx: Context> Profile<Ctx> {
     }
 }
 
+/// Typed getters and setters for common tags. Getters return `None` if the tag is missing or has an unexpected type.
+impl<Ctx: Context> Profile<Ctx> {
+    fn xyz_tag(&self, sig: TagSignature) -> Option<CIEXYZ> {
+        match self.read_tag(sig) {
+            Tag::CIEXYZ(xyz) => Some(*xyz),
+            _ => None,
+        }
+    }
+
+    fn tone_curve_tag(&self, sig: TagSignature) -> Option<&ToneCurveRef> {
+        match self.read_tag(sig) {
+            Tag::ToneCurve(curve) => Some(curve),
+            _ => None,
+        }
+    }
+
+    fn text_tag(&self, sig: TagSignature) -> Option<String> {
+        match self.read_tag(sig) {
+            Tag::MLU(mlu) => mlu.text(Locale::none()).ok(),
+            _ => None,
+        }
+    }
+
+    fn set_text_tag(&mut self, sig: TagSignature, text: &str) -> bool {
+        let mut mlu = MLU::new(1);
+        mlu.set_text(text, Locale::none()) && self.write_tag(sig, Tag::MLU(&mlu))
+    }
+
+    /// `MediaWhitePointTag`
+    #[inline]
+    #[must_use]
+    pub fn media_white_point(&self) -> Option<CIEXYZ> {
+        self.xyz_tag(TagSignature::MediaWhitePointTag)
+    }
+
+    #[inline]
+    pub fn set_media_white_point(&mut self, xyz: &CIEXYZ) -> bool {
+        self.write_tag(TagSignature::MediaWhitePointTag, Tag::CIEXYZ(xyz))
+    }
+
+    /// `MediaBlackPointTag`
+    #[inline]
+    #[must_use]
+    pub fn media_black_point(&self) -> Option<CIEXYZ> {
+        self.xyz_tag(TagSignature::MediaBlackPointTag)
+    }
+
+    #[inline]
+    pub fn set_media_black_point(&mut self, xyz: &CIEXYZ) -> bool {
+        self.write_tag(TagSignature::MediaBlackPointTag, Tag::CIEXYZ(xyz))
+    }
+
+    /// `RedColorantTag` of matrix-shaper profiles
+    #[inline]
+    #[must_use]
+    pub fn red_colorant(&self) -> Option<CIEXYZ> {
+        self.xyz_tag(TagSignature::RedColorantTag)
+    }
+
+    #[inline]
+    pub fn set_red_colorant(&mut self, xyz: &CIEXYZ) -> bool {
+        self.write_tag(TagSignature::RedColorantTag, Tag::CIEXYZ(xyz))
+    }
+
+    /// `GreenColorantTag` of matrix-shaper profiles
+    #[inline]
+    #[must_use]
+    pub fn green_colorant(&self) -> Option<CIEXYZ> {
+        self.xyz_tag(TagSignature::GreenColorantTag)
+    }
+
+    #[inline]
+    pub fn set_green_colorant(&mut self, xyz: &CIEXYZ) -> bool {
+        self.write_tag(TagSignature::GreenColorantTag, Tag::CIEXYZ(xyz))
+    }
+
+    /// `BlueColorantTag` of matrix-shaper profiles
+    #[inline]
+    #[must_use]
+    pub fn blue_colorant(&self) -> Option<CIEXYZ> {
+        self.xyz_tag(TagSignature::BlueColorantTag)
+    }
+
+    #[inline]
+    pub fn set_blue_colorant(&mut self, xyz: &CIEXYZ) -> bool {
+        self.write_tag(TagSignature::BlueColorantTag, Tag::CIEXYZ(xyz))
+    }
+
+    /// `LuminanceTag`, absolute luminance of emissive devices in cd/m² (in the Y component)
+    #[inline]
+    #[must_use]
+    pub fn luminance(&self) -> Option<CIEXYZ> {
+        self.xyz_tag(TagSignature::LuminanceTag)
+    }
+
+    #[inline]
+    pub fn set_luminance(&mut self, xyz: &CIEXYZ) -> bool {
+        self.write_tag(TagSignature::LuminanceTag, Tag::CIEXYZ(xyz))
+    }
+
+    /// `RedTRCTag` of matrix-shaper profiles
+    #[inline]
+    #[must_use]
+    pub fn red_trc(&self) -> Option<&ToneCurveRef> {
+        self.tone_curve_tag(TagSignature::RedTRCTag)
+    }
+
+    #[inline]
+    pub fn set_red_trc(&mut self, curve: &ToneCurveRef) -> bool {
+        self.write_tag(TagSignature::RedTRCTag, Tag::ToneCurve(curve))
+    }
+
+    /// `GreenTRCTag` of matrix-shaper profiles
+    #[inline]
+    #[must_use]
+    pub fn green_trc(&self) -> Option<&ToneCurveRef> {
+        self.tone_curve_tag(TagSignature::GreenTRCTag)
+    }
+
+    #[inline]
+    pub fn set_green_trc(&mut self, curve: &ToneCurveRef) -> bool {
+        self.write_tag(TagSignature::GreenTRCTag, Tag::ToneCurve(curve))
+    }
+
+    /// `BlueTRCTag` of matrix-shaper profiles
+    #[inline]
+    #[must_use]
+    pub fn blue_trc(&self) -> Option<&ToneCurveRef> {
+        self.tone_curve_tag(TagSignature::BlueTRCTag)
+    }
+
+    #[inline]
+    pub fn set_blue_trc(&mut self, curve: &ToneCurveRef) -> bool {
+        self.write_tag(TagSignature::BlueTRCTag, Tag::ToneCurve(curve))
+    }
+
+    /// `GrayTRCTag` of gray profiles
+    #[inline]
+    #[must_use]
+    pub fn gray_trc(&self) -> Option<&ToneCurveRef> {
+        self.tone_curve_tag(TagSignature::GrayTRCTag)
+    }
+
+    #[inline]
+    pub fn set_gray_trc(&mut self, curve: &ToneCurveRef) -> bool {
+        self.write_tag(TagSignature::GrayTRCTag, Tag::ToneCurve(curve))
+    }
+
+    /// `ChromaticAdaptationTag`, the 3x3 matrix (row by row) that converts the actual illumination to D50
+    #[must_use]
+    pub fn chromatic_adaptation_matrix(&self) -> Option<[[f64; 3]; 3]> {
+        // LCMS stores the matrix as 9 doubles, which `Tag` exposes as a triple of xyY
+        match self.read_tag(TagSignature::ChromaticAdaptationTag) {
+            Tag::CIExyYTRIPLE(m) => Some([
+                [m.Red.x, m.Red.y, m.Red.Y],
+                [m.Green.x, m.Green.y, m.Green.Y],
+                [m.Blue.x, m.Blue.y, m.Blue.Y],
+            ]),
+            _ => None,
+        }
+    }
+
+    pub fn set_chromatic_adaptation_matrix(&mut self, m: &[[f64; 3]; 3]) -> bool {
+        let row = |r: &[f64; 3]| CIExyY { x: r[0], y: r[1], Y: r[2] };
+        let m = CIExyYTRIPLE { Red: row(&m[0]), Green: row(&m[1]), Blue: row(&m[2]) };
+        self.write_tag(TagSignature::ChromaticAdaptationTag, Tag::CIExyYTRIPLE(&m))
+    }
+
+    /// `ProfileDescriptionTag` in the default language
+    #[inline]
+    #[must_use]
+    pub fn description(&self) -> Option<String> {
+        self.text_tag(TagSignature::ProfileDescriptionTag)
+    }
+
+    #[inline]
+    pub fn set_description(&mut self, text: &str) -> bool {
+        self.set_text_tag(TagSignature::ProfileDescriptionTag, text)
+    }
+
+    /// `CopyrightTag` in the default language
+    #[inline]
+    #[must_use]
+    pub fn copyright(&self) -> Option<String> {
+        self.text_tag(TagSignature::CopyrightTag)
+    }
+
+    #[inline]
+    pub fn set_copyright(&mut self, text: &str) -> bool {
+        self.set_text_tag(TagSignature::CopyrightTag, text)
+    }
+
+    /// `CicpTag`, coding-independent code points of video signals (ITU-T H.273)
+    #[inline]
+    #[must_use]
+    pub fn cicp(&self) -> Option<VideoSignalType> {
+        match self.read_tag(TagSignature::CicpTag) {
+            Tag::VideoSignal(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn set_cicp(&mut self, cicp: &VideoSignalType) -> bool {
+        self.write_tag(TagSignature::CicpTag, Tag::VideoSignal(cicp))
+    }
+}
+
 impl<Context> Drop for Profile<Context> {
     fn drop(&mut self) {
         unsafe {
@@ -586,6 +1007,13 @@ impl<Context> Drop for Profile<Context> {
     }
 }
 
+// `TagSignature` can't represent private tags, so these take signatures as `u32`
+extern "C" {
+    fn cmsTagLinkedTo(hProfile: ffi::HPROFILE, sig: u32) -> u32;
+    fn cmsReadRawTag(hProfile: ffi::HPROFILE, sig: u32, Buffer: *mut c_void, BufferSize: u32) -> u32;
+    fn cmsWriteRawTag(hProfile: ffi::HPROFILE, sig: u32, data: *const c_void, Size: u32) -> ffi::Bool;
+}
+
 #[test]
 fn tags_read() {
     let prof = Profile::new_srgb();
@@ -634,6 +1062,84 @@ impl fmt::Debug for Profile {
     }
 }
 
+#[test]
+fn raw_tags() {
+    let mut p = Profile::new_srgb();
+    let private = u32::from_be_bytes(*b"prv1");
+    assert!(p.read_raw_tag(private).is_none());
+    let data = b"data\0\0\0\0vendor stuff";
+    assert!(p.write_raw_tag(private, data));
+    assert_eq!(&data[..], &p.read_raw_tag(private).unwrap()[..]);
+
+    let wtpt = p.read_raw_tag(TagSignature::MediaWhitePointTag as u32).unwrap();
+    assert_eq!(b"XYZ ", &wtpt[..4]);
+    assert_eq!(20, wtpt.len());
+
+    // linked tags must have compatible types to survive saving
+    let (red, green) = (TagSignature::RedTRCTag as u32, TagSignature::GreenTRCTag as u32);
+    assert!(p.remove_tag(TagSignature::GreenTRCTag));
+    assert!(p.tag_linked_to(green).is_none());
+    assert!(p.link_tag(TagSignature::GreenTRCTag, TagSignature::RedTRCTag));
+    assert_eq!(Some(red), p.tag_linked_to(green));
+
+    let p = Profile::new_icc(&p.icc().unwrap()).unwrap();
+    assert_eq!(&data[..], &p.read_raw_tag(private).unwrap()[..]);
+    assert!(p.tag_linked_to(green).is_some());
+    assert_eq!(p.read_raw_tag(red), p.read_raw_tag(green));
+}
+
+#[test]
+fn typed_tags() {
+    let mut p = Profile::new_srgb();
+    assert!((p.media_white_point().unwrap().X - CIEXYZ::d50().X).abs() < 0.001);
+    assert!(p.red_colorant().unwrap().X > p.blue_colorant().unwrap().X);
+    assert!(p.green_colorant().unwrap().Y > 0.5);
+    assert!((p.red_trc().unwrap().estimated_gamma(0.1).unwrap() - 2.2).abs() < 0.1);
+    assert!(p.gray_trc().is_none());
+    assert!(p.luminance().is_none());
+    assert!(p.description().unwrap().contains("sRGB"));
+    assert!(p.copyright().is_some());
+    let chad = p.chromatic_adaptation_matrix().unwrap();
+    assert!((chad[0][0] - 1.0479).abs() < 0.001);
+    assert!(p.cicp().is_none());
+
+    assert!(p.set_description("Test"));
+    assert!(p.set_copyright("No rights"));
+    assert!(p.set_luminance(&CIEXYZ { X: 0., Y: 80., Z: 0. }));
+    assert!(p.set_cicp(&VideoSignalType { ColourPrimaries: 1, TransferCharacteristics: 13, MatrixCoefficients: 0, VideoFullRangeFlag: 1 }));
+    assert!(p.set_gray_trc(&ToneCurve::new(1.8)));
+    assert!(p.set_chromatic_adaptation_matrix(&[[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]));
+
+    let p = Profile::new_icc(&p.icc().unwrap()).unwrap();
+    assert_eq!(Some("Test"), p.description().as_deref());
+    assert_eq!(Some("No rights"), p.copyright().as_deref());
+    assert_eq!(80., p.luminance().unwrap().Y.round());
+    assert_eq!(13, p.cicp().unwrap().TransferCharacteristics);
+    assert!((p.gray_trc().unwrap().estimated_gamma(0.1).unwrap() - 1.8).abs() < 0.1);
+    assert_eq!([[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]], p.chromatic_adaptation_matrix().unwrap());
+}
+
+#[test]
+fn creation_date_and_id() {
+    let mut p = Profile::new_srgb();
+    assert!(p.creation_date().unwrap().year >= 2024);
+    assert!(!p.verify_profile_id());
+
+    let date = DateTimeNumber { year: 2001, month: 2, day: 3, hours: 4, minutes: 5, seconds: 6 };
+    assert!(p.set_creation_date(&date));
+    assert_eq!(Some(date), p.creation_date());
+    assert!(p.description().unwrap().contains("sRGB"));
+
+    p.set_default_profile_id();
+    assert!(p.verify_profile_id());
+    let mut p = Profile::new_icc(&p.icc().unwrap()).unwrap();
+    assert_eq!(Some(date), p.creation_date());
+    assert!(p.verify_profile_id());
+
+    assert!(p.set_copyright("Tampered"));
+    assert!(!p.verify_profile_id());
+}
+
 #[test]
 fn setters() {
     let mut p = Profile::new_placeholder();
@@ -649,6 +1155,65 @@ fn icc() {
     assert!(format!("{prof:?}").contains("XYZ identity"));
 }
 
+#[test]
+fn reader_writer() {
+    use std::io::Cursor;
+
+    let srgb = Profile::new_srgb();
+    let mut out = Cursor::new(vec![0xFF; 5]);
+    out.set_position(5);
+    srgb.write_to(&mut out).unwrap();
+    let out = out.into_inner();
+    assert_eq!(&out[5..], &srgb.icc().unwrap()[..]);
+
+    // not seekable
+    let mut vec = Vec::new();
+    srgb.write_to(&mut vec).unwrap();
+    assert_eq!(vec, srgb.icc().unwrap());
+    assert!(srgb.write_to(&mut [0u8; 10][..]).is_err());
+
+    let mut input = Cursor::new(out);
+    input.set_position(5);
+    let prof = Profile::from_reader(input).unwrap();
+    assert_eq!(ColorSpaceSignature::RgbData, prof.color_space());
+    assert!(matches!(prof.read_tag(TagSignature::MediaWhitePointTag), Tag::CIEXYZ(_)));
+    assert_eq!(prof.icc().unwrap(), srgb.icc().unwrap());
+
+    assert!(Profile::from_reader(Cursor::new(vec![0u8; 200])).is_err());
+    assert!(Profile::from_reader(Cursor::new(Vec::new())).is_err());
+}
+
+#[test]
+fn reader_error_after_open() {
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct Flaky(Cursor<Vec<u8>>, Arc<AtomicBool>);
+    impl Read for Flaky {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.1.load(Ordering::SeqCst) {
+                return Err(io::ErrorKind::Other.into());
+            }
+            self.0.read(buf)
+        }
+    }
+    impl Seek for Flaky {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    let srgb = Profile::new_srgb();
+    let failing = Arc::new(AtomicBool::new(false));
+    let prof = Profile::from_reader(Flaky(Cursor::new(srgb.icc().unwrap()), failing.clone())).unwrap();
+    failing.store(true, Ordering::SeqCst);
+    assert!(matches!(prof.read_tag(TagSignature::MediaWhitePointTag), Tag::None));
+    failing.store(false, Ordering::SeqCst);
+    assert!(matches!(prof.read_tag(TagSignature::MediaWhitePointTag), Tag::CIEXYZ(_)));
+    assert_eq!(prof.icc().unwrap(), srgb.icc().unwrap());
+}
+
 #[test]
 fn bad_icc() {
     let err = Profile::new_icc(&[1, 2, 3]);
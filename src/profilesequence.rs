@@ -0,0 +1,249 @@
+use crate::context::Context;
+use crate::{ffi, Error, LCMSResult, MLURef, Profile, Tag, TagSignature};
+use foreign_types::{foreign_type, ForeignTypeRef};
+use std::fmt;
+use std::ptr;
+
+foreign_type! {
+    /// Description of profiles that have been combined into a device link, stored in `ProfileSequenceDescTag` and `ProfileSequenceIdTag`.
+    ///
+    /// See `Profile::profile_sequence()`. Owned version of `ProfileSequenceRef`.
+    pub unsafe type ProfileSequence {
+        type CType = ffi::SEQ;
+        fn drop = ffi::cmsFreeProfileSequenceDescription;
+        fn clone = ffi::cmsDupProfileSequenceDescription;
+    }
+}
+
+/// Information about one profile in a `ProfileSequence`
+#[derive(Debug, Clone, Default)]
+pub struct ProfileSequenceEntry<'a> {
+    /// Device manufacturer signature from the profile's header
+    pub manufacturer: u32,
+    /// Device model signature from the profile's header
+    pub model: u32,
+    /// Device attributes from the profile's header
+    pub attributes: u64,
+    /// Raw `TechnologySignature`, or 0 if unknown. `ProfileSequenceRef::set()` accepts only signatures known to `ffi::TechnologySignature`.
+    pub technology: u32,
+    pub profile_id: ffi::ProfileID,
+    /// `DeviceMfgDescTag` of the profile
+    pub manufacturer_desc: Option<&'a MLURef>,
+    /// `DeviceModelDescTag` of the profile
+    pub model_desc: Option<&'a MLURef>,
+    /// `ProfileDescriptionTag` of the profile
+    pub description: Option<&'a MLURef>,
+}
+
+impl ProfileSequence {
+    /// Allocates a sequence of `len` empty entries. Use `set()` to fill them in.
+    pub fn new(len: usize) -> LCMSResult<Self> {
+        unsafe { Error::if_null(ffi::cmsAllocProfileSequenceDescription(ptr::null_mut(), len as u32)) }
+    }
+
+    /// Describes the profiles in the order they'd be applied, the same way LCMS does it for `Flags::KEEP_SEQUENCE`
+    pub fn from_profiles<Ctx: Context>(profiles: &[&Profile<Ctx>]) -> LCMSResult<Self> {
+        let mut seq = Self::new(profiles.len())?;
+        for (i, p) in profiles.iter().enumerate() {
+            let mlu = |sig| match p.read_tag(sig) {
+                Tag::MLU(mlu) => Some(mlu),
+                _ => None,
+            };
+            let technology = unsafe { ffi::cmsReadTag(p.handle, TagSignature::TechnologyTag).cast::<u32>().as_ref().copied() };
+            let entry = ProfileSequenceEntry {
+                manufacturer: p.header_manufacturer(),
+                model: p.header_model(),
+                attributes: p.header_attributes(),
+                technology: technology.filter(|&t| is_known_technology(t)).unwrap_or(0),
+                profile_id: p.profile_id(),
+                manufacturer_desc: mlu(TagSignature::DeviceMfgDescTag),
+                model_desc: mlu(TagSignature::DeviceModelDescTag),
+                description: mlu(TagSignature::ProfileDescriptionTag),
+            };
+            if !seq.set(i, &entry) {
+                return Err(Error::ObjectCreationError);
+            }
+        }
+        Ok(seq)
+    }
+}
+
+/// The sequence is exposed as `ffi::SEQ`, where `technology` is an enum, so it can't hold arbitrary values
+fn is_known_technology(technology: u32) -> bool {
+    use ffi::TechnologySignature;
+    const KNOWN: [TechnologySignature; 26] = [
+    TechnologySignature::DigitalCamera,
+    TechnologySignature::FilmScanner,
+    TechnologySignature::ReflectiveScanner,
+    TechnologySignature::InkJetPrinter,
+    TechnologySignature::ThermalWaxPrinter,
+    TechnologySignature::ElectrophotographicPrinter,
+    TechnologySignature::ElectrostaticPrinter,
+    TechnologySignature::DyeSublimationPrinter,
+    TechnologySignature::PhotographicPaperPrinter,
+    TechnologySignature::FilmWriter,
+    TechnologySignature::VideoMonitor,
+    TechnologySignature::VideoCamera,
+    TechnologySignature::ProjectionTelevision,
+    TechnologySignature::CRTDisplay,
+    TechnologySignature::PMDisplay,
+    TechnologySignature::AMDisplay,
+    TechnologySignature::PhotoCD,
+    TechnologySignature::PhotoImageSetter,
+    TechnologySignature::Gravure,
+    TechnologySignature::OffsetLithography,
+    TechnologySignature::Silkscreen,
+    TechnologySignature::Flexography,
+    TechnologySignature::MotionPictureFilmScanner,
+    TechnologySignature::MotionPictureFilmRecorder,
+    TechnologySignature::DigitalMotionPictureCamera,
+    TechnologySignature::DigitalCinemaProjector,
+    ];
+    technology == 0 || KNOWN.iter().any(|&t| t as u32 == technology)
+}
+
+impl ProfileSequenceRef {
+    /// Borrows the data of `Tag::SEQ`
+    #[must_use]
+    pub fn from_ffi(seq: &ffi::SEQ) -> &Self {
+        unsafe { Self::from_ptr((seq as *const ffi::SEQ).cast_mut()) }
+    }
+
+    fn seq(&self) -> &ffi::SEQ {
+        unsafe { &*self.as_ptr() }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.seq().n as usize
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<ProfileSequenceEntry<'_>> {
+        if index >= self.len() {
+            return None;
+        }
+        unsafe {
+            let desc = self.seq().seq.add(index);
+            let mlu = |m: *mut ffi::MLU| if m.is_null() { None } else { Some(MLURef::from_ptr(m)) };
+            Some(ProfileSequenceEntry {
+                manufacturer: (*desc).deviceMfg,
+                model: (*desc).deviceModel,
+                attributes: (*desc).attributes,
+                // may not be a known TechnologySignature
+                technology: ptr::addr_of!((*desc).technology).cast::<u32>().read(),
+                profile_id: (*desc).ProfileID,
+                manufacturer_desc: mlu((*desc).Manufacturer),
+                model_desc: mlu((*desc).Model),
+                description: mlu((*desc).Description),
+            })
+        }
+    }
+
+    /// Replaces the entry at `index`. Text is copied.
+    ///
+    /// Fails if the `technology` isn't 0 or a known `TechnologySignature`.
+    pub fn set(&mut self, index: usize, entry: &ProfileSequenceEntry<'_>) -> bool {
+        if index >= self.len() || !is_known_technology(entry.technology) {
+            return false;
+        }
+        unsafe {
+            let desc = self.seq().seq.add(index);
+            (*desc).deviceMfg = entry.manufacturer;
+            (*desc).deviceModel = entry.model;
+            (*desc).attributes = entry.attributes;
+            ptr::addr_of_mut!((*desc).technology).cast::<u32>().write(entry.technology);
+            (*desc).ProfileID = entry.profile_id;
+            replace_mlu(&mut (*desc).Manufacturer, entry.manufacturer_desc);
+            replace_mlu(&mut (*desc).Model, entry.model_desc);
+            replace_mlu(&mut (*desc).Description, entry.description);
+        }
+        true
+    }
+
+    /// `ProfileSequenceIdTag` has more accurate IDs and descriptions
+    pub(crate) fn merge_ids(&mut self, ids: &ProfileSequenceRef) {
+        if ids.len() != self.len() {
+            return;
+        }
+        for i in 0..self.len() {
+            unsafe {
+                let (desc, id) = (self.seq().seq.add(i), ids.seq().seq.add(i));
+                (*desc).ProfileID = (*id).ProfileID;
+                let description = (*id).Description;
+                replace_mlu(&mut (*desc).Description, if description.is_null() { None } else { Some(MLURef::from_ptr(description)) });
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ProfileSequenceEntry<'_>> {
+        (0..self.len()).filter_map(move |i| self.get(i))
+    }
+}
+
+/// `src` may be the same as `dst`
+unsafe fn replace_mlu(dst: &mut *mut ffi::MLU, src: Option<&MLURef>) {
+    let new = src.map_or(ptr::null_mut(), |m| ffi::cmsMLUdup(m.as_ptr()));
+    if !dst.is_null() {
+        ffi::cmsMLUfree(*dst);
+    }
+    *dst = new;
+}
+
+impl fmt::Debug for ProfileSequenceRef {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl fmt::Debug for ProfileSequence {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[test]
+fn profile_sequence() {
+    use crate::*;
+
+    let srgb = Profile::new_srgb();
+    let lab = Profile::new_lab4_context(GlobalContext::new(), &white_point_from_temp(5000.).unwrap()).unwrap();
+    let t = Transform::<[u8; 3], [f32; 3]>::new_flags(&srgb, PixelFormat::RGB_8, &lab, PixelFormat::Lab_FLT, Intent::Perceptual, Flags::KEEP_SEQUENCE).unwrap();
+    let link = Profile::new_device_link(&t, 4.3, Flags::default()).unwrap();
+    let link = Profile::new_icc(&link.icc().unwrap()).unwrap();
+
+    let seq = link.profile_sequence().unwrap();
+    assert_eq!(2, seq.len());
+    let first = seq.get(0).unwrap();
+    assert!(first.description.unwrap().text(Locale::none()).unwrap().contains("sRGB"));
+    assert!(seq.get(1).unwrap().description.is_some());
+    assert!(seq.get(2).is_none());
+
+    let mut custom = ProfileSequence::from_profiles(&[&lab, &srgb]).unwrap();
+    let mut desc = MLU::new(1);
+    desc.set_text("Custom", Locale::none());
+    assert!(custom.set(0, &ProfileSequenceEntry { manufacturer: 0x6C636D73, technology: 0x6463616D, description: Some(&desc), ..Default::default() }));
+    assert!(!custom.set(1, &ProfileSequenceEntry { technology: 0x12345678, ..Default::default() }));
+    drop(desc);
+
+    let mut p = Profile::new_placeholder();
+    p.set_version(4.3);
+    assert!(p.set_profile_sequence(&custom));
+    drop(custom);
+    let p = Profile::new_icc(&p.icc().unwrap()).unwrap();
+    let seq = p.profile_sequence().unwrap();
+    let entries: Vec<_> = seq.iter().collect();
+    assert_eq!(2, entries.len());
+    assert_eq!(0x6C636D73, entries[0].manufacturer);
+    assert_eq!(0x6463616D, entries[0].technology);
+    assert_eq!("Custom", entries[0].description.unwrap().text(Locale::none()).unwrap());
+    assert!(entries[1].description.unwrap().text(Locale::none()).unwrap().contains("sRGB"));
+    assert!(Profile::new_srgb().profile_sequence().is_none());
+}
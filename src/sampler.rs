@@ -0,0 +1,54 @@
+use crate::eval::SamplerFn;
+use std::any::Any;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// State passed as `Cargo` to LCMS sampler callbacks
+pub(crate) struct Sampler<F> {
+    callback: F,
+    inputs: usize,
+    outputs: usize,
+    panic: Option<Box<dyn Any + Send + 'static>>,
+}
+
+impl<F> Sampler<F> {
+    pub fn new(callback: F, inputs: usize, outputs: usize) -> Self {
+        Self { callback, inputs, outputs, panic: None }
+    }
+
+    /// Rethrows a panic that happened in the callback. Returns LCMS's result otherwise.
+    pub fn finish(self, result: i32) -> bool {
+        if let Some(panic) = self.panic {
+            panic::resume_unwind(panic);
+        }
+        result != 0
+    }
+
+    pub fn cargo(&mut self) -> *mut c_void {
+        (self as *mut Self).cast()
+    }
+
+    /// The C callback to pass along with `cargo()`
+    pub fn function<V>(&self) -> SamplerFn<V> where F: FnMut(&[V], &mut [V]) -> bool {
+        sampler::<V, F>
+    }
+}
+
+/// Matches `cmsSAMPLER16` and `cmsSAMPLERFLOAT`. Panics are caught, because they can't unwind through C.
+unsafe extern "C" fn sampler<V, F: FnMut(&[V], &mut [V]) -> bool>(input: *const V, output: *mut V, cargo: *mut c_void) -> i32 {
+    let sampler = &mut *cargo.cast::<Sampler<F>>();
+    if sampler.panic.is_some() {
+        return 0;
+    }
+    let input = slice::from_raw_parts(input, sampler.inputs);
+    let output = if output.is_null() { &mut [][..] } else { slice::from_raw_parts_mut(output, sampler.outputs) };
+    let callback = &mut sampler.callback;
+    match panic::catch_unwind(AssertUnwindSafe(|| callback(input, output))) {
+        Ok(keep_going) => i32::from(keep_going),
+        Err(panic) => {
+            sampler.panic = Some(panic);
+            0
+        },
+    }
+}
@@ -1,8 +1,10 @@
 use crate::context::Context;
 use crate::eval::FloatOrU16;
+use crate::sampler::Sampler;
 use crate::{ffi, Error, GlobalContext, LCMSResult, ToneCurveRef};
 use foreign_types::{foreign_type, ForeignTypeRef};
 use std::fmt;
+use std::os::raw::c_void;
 use std::ptr;
 
 foreign_type! {
@@ -98,6 +100,160 @@ impl StageRef {
     pub fn stage_type(&self) -> ffi::StageSignature {
         unsafe { ffi::cmsStageType(self.as_ptr()) }
     }
+
+    /// Calls the `sampler` callback for every node of a CLUT stage, and stores values it sets in the output slice back in the table.
+    ///
+    /// The callback gets node's coordinates as input (0..=65535 for `u16`, 0..=1 for `f32`), and current values of the node as output.
+    /// Returning `false` from the callback stops sampling.
+    ///
+    /// Returns `false` if the stage isn't a CLUT with `Value` precision (`new_clut::<f32>` needs `f32` here),
+    /// or if the callback has stopped sampling early. Panics in the callback are propagated.
+    pub fn sample_clut<Value: FloatOrU16>(&mut self, sampler: impl FnMut(&[Value], &mut [Value]) -> bool) -> bool {
+        unsafe { self.sample_clut_flags(0, sampler) }
+    }
+
+    /// Same as `sample_clut`, but only reads values of the CLUT nodes (`SAMPLER_INSPECT` flag).
+    pub fn inspect_clut<Value: FloatOrU16>(&self, mut inspector: impl FnMut(&[Value], &[Value]) -> bool) -> bool {
+        unsafe { self.sample_clut_flags(ffi::SAMPLER_INSPECT, move |input, output: &mut [Value]| inspector(input, output)) }
+    }
+
+    /// Without `SAMPLER_INSPECT` this modifies the stage, so it needs exclusive access
+    unsafe fn sample_clut_flags<Value: FloatOrU16, F: FnMut(&[Value], &mut [Value]) -> bool>(&self, flags: u32, callback: F) -> bool {
+        match self.clut_data() {
+            Some(clut) if (clut.has_float_values != 0) == Value::IS_FLOAT => {},
+            _ => return false,
+        }
+        let mut cargo = Sampler::new(callback, self.input_channels(), self.output_channels());
+        let res = Value::stage_sample_clut(self.as_ptr(), cargo.function(), cargo.cargo(), flags);
+        cargo.finish(res)
+    }
+
+    /// Contents of the stage, for the stage types that have a known layout. Borrows data of the stage without copying.
+    #[must_use]
+    pub fn data(&self) -> StageData<'_> {
+        unsafe {
+            let data = ffi::cmsStageData(self.as_ptr());
+            if data.is_null() {
+                return StageData::Other;
+            }
+            match self.stage_type() {
+                ffi::StageSignature::MatrixElemType => {
+                    let data = &*data.cast::<MatrixData>();
+                    let rows = self.output_channels();
+                    StageData::Matrix {
+                        matrix: slice_or_empty(data.double, rows * self.input_channels()),
+                        offset: if data.offset.is_null() { None } else { Some(slice_or_empty(data.offset, rows)) },
+                    }
+                },
+                ffi::StageSignature::CurveSetElemType => {
+                    let data = &*data.cast::<ToneCurvesData>();
+                    StageData::ToneCurves(slice_or_empty(data.the_curves.cast::<&ToneCurveRef>(), data.n_curves as usize))
+                },
+                ffi::StageSignature::CLutElemType => {
+                    let Some(clut) = self.clut_data() else { return StageData::Other };
+                    let Some(params) = clut.params.as_ref() else { return StageData::Other };
+                    let grid_points = &params.n_samples[..(params.n_inputs as usize).min(MAX_INPUT_DIMENSIONS)];
+                    let entries = clut.n_entries as usize;
+                    if clut.has_float_values != 0 {
+                        StageData::CLutFloat { grid_points, table: slice_or_empty(clut.tab.cast::<f32>(), entries) }
+                    } else {
+                        StageData::CLut16 { grid_points, table: slice_or_empty(clut.tab.cast::<u16>(), entries) }
+                    }
+                },
+                _ => StageData::Other,
+            }
+        }
+    }
+
+    fn clut_data(&self) -> Option<&CLutData> {
+        if self.stage_type() != ffi::StageSignature::CLutElemType {
+            return None;
+        }
+        unsafe { ffi::cmsStageData(self.as_ptr()).cast::<CLutData>().as_ref() }
+    }
+}
+
+/// Contents of a stage, see [`StageRef::data`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StageData<'a> {
+    /// Row-major matrix with a row for every output channel and a column for every input channel
+    Matrix {
+        matrix: &'a [f64],
+        /// One per output channel
+        offset: Option<&'a [f64]>,
+    },
+    /// One curve per channel
+    ToneCurves(&'a [&'a ToneCurveRef]),
+    /// Lookup table with `grid_points` nodes in each input dimension, and output channels interleaved
+    CLut16 {
+        grid_points: &'a [u32],
+        table: &'a [u16],
+    },
+    /// Same as `CLut16`, but for `new_clut::<f32>`
+    CLutFloat {
+        grid_points: &'a [u32],
+        table: &'a [f32],
+    },
+    /// Other stage types are not exposed
+    Other,
+}
+
+unsafe fn slice_or_empty<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 { &[] } else { std::slice::from_raw_parts(ptr, len) }
+}
+
+const MAX_INPUT_DIMENSIONS: usize = 15;
+
+/// Layout of `_cmsStageCLutData` from `lcms2_plugin.h`
+#[repr(C)]
+struct CLutData {
+    tab: *mut c_void,
+    params: *const InterpParams,
+    n_entries: u32,
+    has_float_values: i32,
+}
+
+/// Beginning of `cmsInterpParams` from `lcms2_plugin.h`. The remaining fields aren't used.
+#[repr(C)]
+struct InterpParams {
+    context_id: ffi::Context,
+    flags: u32,
+    n_inputs: u32,
+    n_outputs: u32,
+    n_samples: [u32; MAX_INPUT_DIMENSIONS],
+}
+
+/// Layout of `_cmsStageMatrixData`
+#[repr(C)]
+struct MatrixData {
+    double: *const f64,
+    offset: *const f64,
+}
+
+/// Layout of `_cmsStageToneCurvesData`
+#[repr(C)]
+struct ToneCurvesData {
+    n_curves: u32,
+    the_curves: *const *mut ffi::ToneCurve,
+}
+
+/// Walks a regular N-dimensional grid, calling the callback with coordinates of every node.
+///
+/// `grid_points` has number of nodes in each dimension (at least 2). Coordinates are spread evenly over 0..=65535 for `u16`, and 0..=1 for `f32`.
+/// The last dimension changes fastest. Returning `false` from the callback stops the walk.
+///
+/// Returns `false` if the walk has been stopped, or the grid is invalid (too many dimensions, too few or too many points).
+/// Panics in the callback are propagated.
+pub fn slice_space<Value: FloatOrU16>(grid_points: &[u32], mut callback: impl FnMut(&[Value]) -> bool) -> bool {
+    if grid_points.len() >= ffi::MAXCHANNELS {
+        return false;
+    }
+    let mut cargo = Sampler::new(move |input: &[Value], _: &mut [Value]| callback(input), grid_points.len(), 0);
+    unsafe {
+        let res = Value::slice_space(grid_points.len() as u32, grid_points.as_ptr(), cargo.function(), cargo.cargo());
+        cargo.finish(res)
+    }
 }
 
 pub struct StagesIter<'a>(pub Option<&'a StageRef>);
@@ -120,9 +276,101 @@ impl<'a> Iterator for StagesIter<'a> {
     }
 }
 
+impl fmt::Debug for Stage {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 impl fmt::Debug for StageRef {
     #[cold]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Stage({:?})", self.stage_type())
     }
 }
+
+#[test]
+fn sample_clut() {
+    let mut clut = Stage::new_clut::<u16>(3, 2, 1, None).unwrap();
+    assert!(clut.sample_clut(|input: &[u16], output: &mut [u16]| {
+        output[0] = input[0] / 2 + input[1] / 4;
+        true
+    }));
+    let mut nodes = Vec::new();
+    assert!(clut.inspect_clut(|input: &[u16], output: &[u16]| {
+        nodes.push((input.to_vec(), output[0]));
+        true
+    }));
+    assert_eq!(9, nodes.len());
+    assert_eq!((vec![0, 0], 0), nodes[0]);
+    assert_eq!((vec![0, 32768], 8192), nodes[1]);
+    assert_eq!((vec![65535, 65535], 32767 + 16383), nodes[8]);
+
+    let mut count = 0;
+    assert!(!clut.inspect_clut(|_: &[u16], _: &[u16]| { count += 1; count < 3 }));
+    assert_eq!(3, count);
+    assert!(!clut.inspect_clut(|_: &[f32], _: &[f32]| true));
+    assert!(!Stage::new_identity(3).inspect_clut(|_: &[u16], _: &[u16]| true));
+
+    let mut clut = Stage::new_clut::<f32>(2, 3, 3, None).unwrap();
+    assert!(clut.sample_clut(|input: &[f32], output: &mut [f32]| {
+        output.copy_from_slice(input);
+        true
+    }));
+    assert!(clut.inspect_clut(|input: &[f32], output: &[f32]| input == output));
+
+    let res = std::panic::catch_unwind(|| {
+        Stage::new_clut::<u16>(2, 1, 1, None).unwrap().sample_clut(|_: &[u16], _: &mut [u16]| panic!("oops"))
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+fn slice() {
+    let mut nodes = Vec::new();
+    assert!(slice_space(&[2, 3], |input: &[u16]| { nodes.push([input[0], input[1]]); true }));
+    assert_eq!(nodes, [[0, 0], [0, 32768], [0, 65535], [65535, 0], [65535, 32768], [65535, 65535]]);
+
+    let mut count = 0;
+    assert!(!slice_space(&[5, 5, 5], |input: &[f32]| { count += 1; input[2] < 0.5 }));
+    assert_eq!(3, count);
+
+    assert!(!slice_space(&[3, 1], |_: &[f32]| true));
+    assert!(!slice_space(&[2; 20], |_: &[u16]| true));
+}
+
+#[test]
+fn stage_data() {
+    let m = Stage::new_matrix(&[1., 2., 3., 4.], 2, 2, Some(&[0.5, 0.25])).unwrap();
+    match m.data() {
+        StageData::Matrix { matrix, offset } => {
+            assert_eq!(matrix, &[1., 2., 3., 4.]);
+            assert_eq!(offset, Some(&[0.5, 0.25][..]));
+        },
+        other => panic!("{other:?}"),
+    }
+    let m = Stage::new_matrix(&[1., 2., 3.], 1, 3, None).unwrap();
+    assert!(matches!(m.data(), StageData::Matrix { offset: None, matrix: &[1., 2., 3.] }));
+
+    let gamma = crate::ToneCurve::new(2.2);
+    let curves = Stage::new_tone_curves(&[&gamma, &gamma]).unwrap();
+    match curves.data() {
+        StageData::ToneCurves(c) => {
+            assert_eq!(2, c.len());
+            assert!((c[1].estimated_gamma(0.1).unwrap() - 2.2).abs() < 0.01);
+        },
+        other => panic!("{other:?}"),
+    }
+
+    let clut = Stage::new_clut(3, 2, 1, Some(&[1u16, 2, 3, 4, 5, 6, 7, 8, 9])).unwrap();
+    match clut.data() {
+        StageData::CLut16 { grid_points, table } => {
+            assert_eq!(grid_points, &[3, 3]);
+            assert_eq!(table, &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        },
+        other => panic!("{other:?}"),
+    }
+    let clut = Stage::new_clut::<f32>(2, 3, 2, None).unwrap();
+    assert!(matches!(clut.data(), StageData::CLutFloat { grid_points: &[2, 2, 2], table } if table.len() == 16));
+}
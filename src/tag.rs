@@ -1,5 +1,7 @@
 use crate::*;
 use foreign_types::ForeignTypeRef;
+use std::fmt;
+use std::mem;
 
 unsafe fn cast<T>(ptr: *const u8) -> &'static T {
     assert!(0 == ptr.align_offset(std::mem::align_of::<T>()), "Tag data pointer must be aligned");
@@ -111,6 +113,7 @@ impl<'a> Tag<'a> {
             (MHC2Tag, &Tag::MHC2(data)) => {
                 data as *const ffi::MHC2Type as *const u8
             },
+            (MetaTag, &Tag::Dict(data)) => data.as_ptr() as *const u8,
             (sig, _) => panic!("Signature type {sig:?} does not support this tag data type"),
         }
     }
@@ -194,11 +197,175 @@ impl<'a> Tag<'a> {
             ViewingConditionsTag => Tag::ICCViewingConditions(cast(data)),
             CicpTag => Tag::VideoSignal(cast(data)),
             MHC2Tag => Tag::MHC2(cast(data)),
+            MetaTag => Tag::Dict(DictRef::from_ptr(aligned_mut(data))),
             _ => Tag::None,
         }
     }
 }
 
+/// Owned value of a tag. Unlike `Tag`, it doesn't borrow from the `Profile`, so it can be kept after the profile is modified or dropped.
+///
+/// See `Profile::read_tag_owned()` and `Profile::write_tag_value()`.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum TagValue {
+    CIExyYTRIPLE(ffi::CIExyYTRIPLE),
+    CIEXYZ(ffi::CIEXYZ),
+    ICCData(ICCDataBuf),
+    ICCMeasurementConditions(ffi::ICCMeasurementConditions),
+    ICCViewingConditions(ffi::ICCViewingConditions),
+    /// Unicode string
+    MLU(MLU),
+    /// A palette
+    NamedColorList(NamedColorList),
+    Pipeline(Pipeline),
+    Screening(Box<ffi::Screening>),
+    Intent(Intent),
+    ColorimetricIntentImageState(ffi::ColorimetricIntentImageState),
+    Technology(ffi::TechnologySignature),
+    ToneCurve(ToneCurve),
+    VcgtCurves([ToneCurve; 3]),
+    VideoSignal(ffi::VideoSignalType),
+    /// Key/value metadata
+    Dict(Dict),
+    ProfileSequence(ProfileSequence),
+}
+
+impl TagValue {
+    /// Copies the tag's data.
+    ///
+    /// Returns `None` for `Tag::None`, and for tags that contain pointers to other data (`UcrBg`, `MHC2`), which can't be copied yet.
+    #[must_use]
+    pub fn from_tag(tag: &Tag<'_>) -> Option<Self> {
+        Some(match *tag {
+            Tag::CIExyYTRIPLE(v) => Self::CIExyYTRIPLE(*v),
+            Tag::CIEXYZ(v) => Self::CIEXYZ(*v),
+            Tag::ICCData(v) => Self::ICCData(ICCDataBuf::from_ffi(v)),
+            Tag::ICCMeasurementConditions(v) => Self::ICCMeasurementConditions(*v),
+            Tag::ICCViewingConditions(v) => Self::ICCViewingConditions(*v),
+            Tag::MLU(v) => Self::MLU(v.to_owned()),
+            Tag::NamedColorList(v) => Self::NamedColorList(v.to_owned()),
+            Tag::Pipeline(v) => Self::Pipeline(v.to_owned()),
+            Tag::Screening(v) => Self::Screening(Box::new(*v)),
+            Tag::Intent(v) => Self::Intent(v),
+            Tag::ColorimetricIntentImageState(v) => Self::ColorimetricIntentImageState(v),
+            Tag::Technology(v) => Self::Technology(v),
+            Tag::ToneCurve(v) => Self::ToneCurve(v.to_owned()),
+            Tag::VcgtCurves([r, g, b]) => Self::VcgtCurves([r.to_owned(), g.to_owned(), b.to_owned()]),
+            Tag::VideoSignal(v) => Self::VideoSignal(*v),
+            Tag::Dict(v) => Self::Dict(v.to_owned()),
+            Tag::SEQ(v) => Self::ProfileSequence(ProfileSequenceRef::from_ffi(v).to_owned()),
+            Tag::UcrBg(_) | Tag::MHC2(_) | Tag::None => return None,
+        })
+    }
+
+    /// Borrows the value as a `Tag`, e.g. for `Profile::write_tag()`
+    #[must_use]
+    pub fn as_tag(&self) -> Tag<'_> {
+        match self {
+            Self::CIExyYTRIPLE(v) => Tag::CIExyYTRIPLE(v),
+            Self::CIEXYZ(v) => Tag::CIEXYZ(v),
+            Self::ICCData(v) => Tag::ICCData(v.as_ffi()),
+            Self::ICCMeasurementConditions(v) => Tag::ICCMeasurementConditions(v),
+            Self::ICCViewingConditions(v) => Tag::ICCViewingConditions(v),
+            Self::MLU(v) => Tag::MLU(v),
+            Self::NamedColorList(v) => Tag::NamedColorList(v),
+            Self::Pipeline(v) => Tag::Pipeline(v),
+            Self::Screening(v) => Tag::Screening(v),
+            Self::Intent(v) => Tag::Intent(*v),
+            Self::ColorimetricIntentImageState(v) => Tag::ColorimetricIntentImageState(*v),
+            Self::Technology(v) => Tag::Technology(*v),
+            Self::ToneCurve(v) => Tag::ToneCurve(v),
+            Self::VcgtCurves([r, g, b]) => Tag::VcgtCurves([r, g, b]),
+            Self::VideoSignal(v) => Tag::VideoSignal(v),
+            Self::Dict(v) => Tag::Dict(v),
+            Self::ProfileSequence(v) => Tag::SEQ(unsafe { &*v.as_ptr() }),
+        }
+    }
+}
+
+/// Owned `ffi::ICCData`: a blob of bytes with a flag (0 for ASCII, 1 for binary data).
+///
+/// The C struct is variable-length, so it's stored in a buffer that keeps the bytes right after the header.
+#[derive(Clone)]
+pub struct ICCDataBuf {
+    /// `len`, `flag`, and then the data
+    buf: Vec<u32>,
+}
+
+impl ICCDataBuf {
+    const HEADER: usize = 2;
+
+    #[must_use]
+    pub fn new(flag: u32, data: &[u8]) -> Self {
+        let words = (data.len() + 3) / 4;
+        // the C struct has at least one byte of data
+        let mut buf = vec![0u32; (Self::HEADER + words).max(mem::size_of::<ffi::ICCData>() / 4)];
+        buf[0] = data.len() as u32;
+        buf[1] = flag;
+        bytemuck::cast_slice_mut::<u32, u8>(&mut buf[Self::HEADER..])[..data.len()].copy_from_slice(data);
+        Self { buf }
+    }
+
+    fn from_ffi(data: &ffi::ICCData) -> Self {
+        let bytes = unsafe { std::slice::from_raw_parts(data.data.as_ptr(), data.len as usize) };
+        Self::new(data.flag, bytes)
+    }
+
+    #[must_use]
+    pub fn flag(&self) -> u32 {
+        self.buf[1]
+    }
+
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &bytemuck::cast_slice::<u32, u8>(&self.buf[Self::HEADER..])[..self.buf[0] as usize]
+    }
+
+    #[must_use]
+    pub fn as_ffi(&self) -> &ffi::ICCData {
+        unsafe { &*self.buf.as_ptr().cast::<ffi::ICCData>() }
+    }
+}
+
+impl fmt::Debug for ICCDataBuf {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ICCData({} bytes, flag {})", self.data().len(), self.flag())
+    }
+}
+
+#[test]
+fn tag_values() {
+    let mut icc = Profile::new_srgb();
+    let desc = icc.read_tag_owned(TagSignature::ProfileDescriptionTag).unwrap();
+    let TagValue::ToneCurve(trc) = icc.read_tag_owned(TagSignature::RedTRCTag).unwrap() else { panic!() };
+    let wtpt = icc.read_tag_owned(TagSignature::MediaWhitePointTag).unwrap();
+    assert!(icc.read_tag_owned(TagSignature::BToA0Tag).is_none());
+
+    let mut mlu = MLU::new(1);
+    mlu.set_text("Changed", Locale::none());
+    assert!(icc.write_tag_value(TagSignature::ProfileDescriptionTag, &TagValue::MLU(mlu)));
+    drop(icc);
+
+    let TagValue::MLU(desc) = desc else { panic!() };
+    assert!(desc.text(Locale::none()).unwrap().contains("sRGB"));
+    assert!((trc.estimated_gamma(0.1).unwrap() - 2.2).abs() < 0.1);
+
+    let data = TagValue::ICCData(ICCDataBuf::new(1, b"hello world"));
+    let mut p = Profile::new_placeholder();
+    assert!(p.write_tag_value(TagSignature::MediaWhitePointTag, &wtpt));
+    assert!(p.write_tag_value(TagSignature::Ps2CSATag, &data));
+    assert!(p.write_tag_value(TagSignature::VcgtTag, &TagValue::VcgtCurves([trc.clone(), trc.clone(), trc])));
+    drop(data);
+
+    let p = Profile::new_icc(&p.icc().unwrap()).unwrap();
+    let TagValue::ICCData(data) = p.read_tag_owned(TagSignature::Ps2CSATag).unwrap() else { panic!() };
+    assert_eq!((1, &b"hello world"[..]), (data.flag(), data.data()));
+    assert!(matches!(p.read_tag_owned(TagSignature::MediaWhitePointTag), Some(TagValue::CIEXYZ(xyz)) if (xyz.X - CIEXYZ::d50().X).abs() < 0.001));
+    assert!(matches!(p.read_tag_owned(TagSignature::VcgtTag), Some(TagValue::VcgtCurves(_))));
+}
+
 #[test]
 fn tone_curves_tag() {
     let mut icc = Profile::new_srgb();
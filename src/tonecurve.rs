@@ -203,6 +203,13 @@ impl fmt::Debug for ToneCurveRef {
     }
 }
 
+impl fmt::Debug for ToneCurve {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 #[test]
 fn tones() {
     let _ = ToneCurve::new(0.);
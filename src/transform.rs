@@ -1,4 +1,4 @@
-use crate::context::Context;
+use crate::context::{capture_errors, Context};
 use crate::*;
 use std::fmt;
 use std::marker::PhantomData;
@@ -28,6 +28,7 @@ use std::os::raw::c_void;
 ///  * `InputPixelFormat` — e.g. `(u8,u8,u8)` or struct `RGB<u8>`, etc.
 ///     The type must have appropriate number of bytes per pixel (i.e. you can't just use `[u8]` for everything).
 ///  * `OutputPixelFormat` — similar to `InputPixelFormat`. If both are the same, then `transform_in_place()` function works.
+///    For planar formats it's the type of a single sample instead, e.g. `u8` for `RGB_8_PLANAR` (see `transform_planar()`).
 ///  * `Context` — it's `GlobalContext` for the default non-thread-safe version, or `ThreadContext` for thread-safe version.
 ///  * `Flags` — `AllowCache` or `DisallowCache`. If you disallow cache, then the transform will be accessible from multiple threads.
 ///
@@ -36,6 +37,9 @@ use std::os::raw::c_void;
 ///  * Transform is `Send` if you create it with `ThreadContext` (use `new_*_context()` functions).
 ///  * Transform is `Sync` if you create it without cache. Set flags to `Flags::NO_CACHE`.
 ///
+/// Instead of the `new_*` constructors, you can use `TransformBuilder`, which supports all of their options,
+/// and returns an error instead of panicking when the pixel types don't match the formats.
+///
 #[repr(transparent)]
 pub struct Transform<InputPixelFormat, OutputPixelFormat, Context = GlobalContext, Flags = AllowCache> {
     pub(crate) handle: ffi::HTRANSFORM,
@@ -116,6 +120,73 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod> Transform<Inpu
     pub fn new_multiprofile(profiles: &[&Profile], in_format: PixelFormat, out_format: PixelFormat, intent: Intent, flags: Flags) -> LCMSResult<Self> {
         Self::new_multiprofile_context(GlobalContext::new(), profiles, in_format, out_format, intent, flags)
     }
+
+    /// Same as [`Transform::new`], but returns `Error::PixelFormatMismatch` instead of panicking if the pixel types don't match the formats.
+    #[inline]
+    pub fn try_new(input: &Profile, in_format: PixelFormat, output: &Profile, out_format: PixelFormat, intent: Intent) -> LCMSResult<Self> {
+        Self::try_new_flags(input, in_format, output, out_format, intent, Flags::default())
+    }
+
+    /// Same as [`Transform::new_flags`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_new_flags<Fl: CacheFlag>(input: &Profile, in_format: PixelFormat, output: &Profile, out_format: PixelFormat, intent: Intent, flags: Flags<Fl>) -> LCMSResult<Self> {
+        Self::try_new_flags_context(GlobalContext::new(), input, in_format, output, out_format, intent, flags.allow_cache())
+    }
+
+    /// Same as [`Transform::new_proofing`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_new_proofing(input: &Profile, in_format: PixelFormat, output: &Profile, out_format: PixelFormat,
+                            proofing: &Profile, intent: Intent, proofing_intent: Intent, flags: Flags) -> LCMSResult<Self> {
+        Self::try_new_proofing_context(GlobalContext::new(), input, in_format, output, out_format, proofing, intent, proofing_intent, flags)
+    }
+
+    /// Same as [`Transform::new_multiprofile`], but returns an error instead of panicking.
+    #[inline]
+    pub fn try_new_multiprofile(profiles: &[&Profile], in_format: PixelFormat, out_format: PixelFormat, intent: Intent, flags: Flags) -> LCMSResult<Self> {
+        Self::try_new_multiprofile_context(GlobalContext::new(), profiles, in_format, out_format, intent, flags)
+    }
+}
+
+impl<InputPixelFormat: LcmsPixel, OutputPixelFormat: LcmsPixel> Transform<InputPixelFormat, OutputPixelFormat, GlobalContext, AllowCache> {
+    /// Creates a transform for the given pixel types, using their `LcmsPixel::FORMAT`.
+    ///
+    /// ```rust,ignore
+    /// let t = Transform::<RGB8, RGBA16>::new_typed(&input, &output, Intent::Perceptual)?;
+    /// ```
+    ///
+    /// Pixel types with a `FORMAT` that doesn't match their size fail to compile.
+    #[inline]
+    pub fn new_typed(input: &Profile, output: &Profile, intent: Intent) -> LCMSResult<Self> {
+        let () = CheckPixel::<InputPixelFormat>::SIZE_MATCHES;
+        let () = CheckPixel::<OutputPixelFormat>::SIZE_MATCHES;
+        Self::try_new(input, InputPixelFormat::FORMAT, output, OutputPixelFormat::FORMAT, intent)
+    }
+
+    /// Same as `new_typed()`, with flags like in `new_flags()`
+    #[inline]
+    pub fn new_typed_flags<Fl: CacheFlag>(input: &Profile, output: &Profile, intent: Intent, flags: Flags<Fl>) -> LCMSResult<Self> {
+        let () = CheckPixel::<InputPixelFormat>::SIZE_MATCHES;
+        let () = CheckPixel::<OutputPixelFormat>::SIZE_MATCHES;
+        Self::try_new_flags(input, InputPixelFormat::FORMAT, output, OutputPixelFormat::FORMAT, intent, flags)
+    }
+}
+
+impl<InputPixelFormat: LcmsPixel, OutputPixelFormat: LcmsPixel, Ctx: Context> Transform<InputPixelFormat, OutputPixelFormat, Ctx, AllowCache> {
+    /// Same as `new_typed()`, but with a thread-safe context like in `new_context()`
+    #[inline]
+    pub fn new_typed_context(context: impl AsRef<Ctx>, input: &Profile<Ctx>, output: &Profile<Ctx>, intent: Intent) -> LCMSResult<Self> {
+        Self::new_typed_flags_context(context, input, output, intent, Flags::default())
+    }
+}
+
+impl<InputPixelFormat: LcmsPixel, OutputPixelFormat: LcmsPixel, Ctx: Context, Fl: CacheFlag> Transform<InputPixelFormat, OutputPixelFormat, Ctx, Fl> {
+    /// Same as `new_typed()`, with a context and flags like in `new_flags_context()`. Use `Flags::NO_CACHE` to make the transform `Sync`.
+    #[inline]
+    pub fn new_typed_flags_context(context: impl AsRef<Ctx>, input: &Profile<Ctx>, output: &Profile<Ctx>, intent: Intent, flags: Flags<Fl>) -> LCMSResult<Self> {
+        let () = CheckPixel::<InputPixelFormat>::SIZE_MATCHES;
+        let () = CheckPixel::<OutputPixelFormat>::SIZE_MATCHES;
+        Self::try_new_flags_context(context, input, InputPixelFormat::FORMAT, output, OutputPixelFormat::FORMAT, intent, flags)
+    }
 }
 
 impl<PixelFormat: Copy + Pod, Ctx: Context, Fl: CacheFlag> Transform<PixelFormat, PixelFormat, Ctx, Fl> {
@@ -137,6 +208,20 @@ impl<PixelFormat: Copy + Pod, Ctx: Context, Fl: CacheFlag> Transform<PixelFormat
                                 num_pixels);
         }
     }
+
+    /// Same as `transform_in_place()`, but returns `Error::PixelFormatMismatch` instead of panicking
+    /// if a `[u8]` slice has a partial pixel, or the format is planar.
+    #[inline]
+    pub fn try_transform_in_place(&self, srcdst: &mut [PixelFormat]) -> LCMSResult<()> {
+        let num_pixels = self.try_num_pixels(srcdst.len(), srcdst.len())?;
+        unsafe {
+            ffi::cmsDoTransform(self.handle,
+                                srcdst.as_ptr().cast::<c_void>(),
+                                srcdst.as_mut_ptr().cast::<c_void>(),
+                                num_pixels);
+        }
+        Ok(())
+    }
 }
 
 impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context> Transform<InputPixelFormat, OutputPixelFormat, Ctx, AllowCache> {
@@ -148,11 +233,18 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context>
                        output: &Profile<Ctx>, out_format: PixelFormat, intent: Intent) -> LCMSResult<Self> {
         Self::new_flags_context(context, input, in_format, output, out_format, intent, Flags::default())
     }
+
+    /// Same as `new_context()`, but returns `Error::PixelFormatMismatch` instead of panicking if the pixel types don't match the formats.
+    #[inline]
+    pub fn try_new_context(context: impl AsRef<Ctx>, input: &Profile<Ctx>, in_format: PixelFormat,
+                           output: &Profile<Ctx>, out_format: PixelFormat, intent: Intent) -> LCMSResult<Self> {
+        Self::try_new_flags_context(context, input, in_format, output, out_format, intent, Flags::default())
+    }
 }
 
 impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context, Fl: CacheFlag> Transform<InputPixelFormat, OutputPixelFormat, Ctx, Fl> {
     #[inline]
-    unsafe fn new_handle(handle: ffi::HTRANSFORM) -> LCMSResult<Self> {
+    pub(crate) unsafe fn new_handle(handle: ffi::HTRANSFORM) -> LCMSResult<Self> {
         if handle.is_null() {
             Err(Error::ObjectCreationError)
         } else {
@@ -167,24 +259,69 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
     }
 
     #[track_caller]
-    fn check_formats(in_format: PixelFormat, out_format: PixelFormat) {
-        Self::check_format::<InputPixelFormat>(in_format, true);
-        Self::check_format::<OutputPixelFormat>(out_format, false);
+    pub(crate) fn check_formats(in_format: PixelFormat, out_format: PixelFormat) {
+        if let Err(err) = Self::try_check_formats(in_format, out_format) {
+            panic!("{err}");
+        }
     }
 
-    #[track_caller]
-    fn check_format<P: Copy + Pod>(format: PixelFormat, input: bool) {
-        let io = if input {"input"} else {"output"};
-        assert!(!format.planar(), "Planar {format:?} {io} format not supported");
+    pub(crate) fn try_check_formats(in_format: PixelFormat, out_format: PixelFormat) -> LCMSResult<()> {
+        Self::check_format::<InputPixelFormat>(in_format, true)
+            .and_then(|_| Self::check_format::<OutputPixelFormat>(out_format, false))
+            .map_err(Error::PixelFormatMismatch)
+    }
+
+    fn check_format<P: Copy + Pod>(format: PixelFormat, input: bool) -> Result<(), PixelFormatError> {
+        let size = std::mem::size_of::<P>();
         // Special-case u8
         if is_u8::<P>() {
-            return;
+            return Ok(());
+        }
+        // Planar slices contain individual samples, not whole pixels
+        if format.planar() {
+            if format.bytes_per_channel() != size {
+                return Err(PixelFormatError::BytesPerSample { format, input, expected: format.bytes_per_channel(), actual: size });
+            }
+            return Ok(());
+        }
+        if format.bytes_per_pixel() != size {
+            return Err(PixelFormatError::BytesPerPixel { format, input, expected: format.bytes_per_pixel(), actual: size });
+        }
+        Ok(())
+    }
+
+    /// Reuses the precalculated transform for different pixel layouts, e.g. `RGB_16` and `BGRA_16`, without creating it again (`cmsChangeBuffersFormat`).
+    ///
+    /// LCMS allows this only for transforms that have been created with an input format of at least 16 bits per channel,
+    /// and the new formats must have the same channels. Pixel types are checked like in `try_new()`.
+    ///
+    /// If the formats can't be changed, the unchanged transform is returned with the error.
+    pub fn with_formats<NewInputPixelFormat: Copy + Pod, NewOutputPixelFormat: Copy + Pod>(self, in_format: PixelFormat, out_format: PixelFormat)
+        -> Result<Transform<NewInputPixelFormat, NewOutputPixelFormat, Ctx, Fl>, (Self, Error)> {
+        if let Err(err) = Transform::<NewInputPixelFormat, NewOutputPixelFormat, Ctx, Fl>::try_check_formats(in_format, out_format) {
+            return Err((self, err));
+        }
+        let context = unsafe { ffi::cmsGetTransformContextID(self.handle) };
+        let res = capture_errors(context, "Transform::with_formats", || unsafe {
+            if ffi::cmsChangeBuffersFormat(self.handle, in_format, out_format) == 0 {
+                return Err(Error::ObjectCreationError);
+            }
+            Ok(())
+        });
+        match res {
+            Ok(()) => {
+                let handle = self.handle;
+                std::mem::forget(self);
+                Ok(Transform {
+                    handle,
+                    _from: PhantomData,
+                    _to: PhantomData,
+                    _context_ref: PhantomData,
+                    _flags_ref: PhantomData,
+                })
+            },
+            Err(err) => Err((self, err)),
         }
-        assert_eq!(format.bytes_per_pixel(),
-                   std::mem::size_of::<P>(),
-                   "{format:?} has {} bytes per pixel, but the {io} format has {}",
-                   format.bytes_per_pixel(),
-                   std::mem::size_of::<P>());
     }
 
     /// Description of the input pixel format this transform has been created for
@@ -201,22 +338,49 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
 
     #[inline]
     #[track_caller]
-    fn num_pixels(&self, mut src_len: usize, mut dst_len: usize) -> u32 {
+    fn num_pixels(&self, src_len: usize, dst_len: usize) -> u32 {
+        self.pixel_count(src_len, dst_len).min(u32::MAX as usize) as u32
+    }
+
+    #[inline]
+    fn try_num_pixels(&self, src_len: usize, dst_len: usize) -> LCMSResult<u32> {
+        let count = self.try_pixel_count(src_len, dst_len).map_err(Error::PixelFormatMismatch)?;
+        Ok(count.min(u32::MAX as usize) as u32)
+    }
+
+    #[track_caller]
+    pub(crate) fn pixel_count(&self, src_len: usize, dst_len: usize) -> usize {
+        match self.try_pixel_count(src_len, dst_len) {
+            Ok(count) => count,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    fn try_pixel_count(&self, mut src_len: usize, mut dst_len: usize) -> Result<usize, PixelFormatError> {
+        let in_format = self.input_pixel_format();
+        let out_format = self.output_pixel_format();
+        if in_format.planar() || out_format.planar() {
+            return Err(PixelFormatError::PlanarUnsupported { in_format, out_format });
+        }
         if is_u8::<InputPixelFormat>() {
-            let bpp = self.input_pixel_format().bytes_per_pixel();
+            let bpp = in_format.bytes_per_pixel();
             if bpp > 1 {
-                assert_eq!(0, src_len % bpp, "Input [u8] slice's length {src_len} is not a multiple of {bpp}");
+                if src_len % bpp != 0 {
+                    return Err(PixelFormatError::PartialPixel { input: true, len: src_len, bytes_per_pixel: bpp });
+                }
                 src_len /= bpp;
             }
         }
         if is_u8::<OutputPixelFormat>() {
-            let bpp = self.output_pixel_format().bytes_per_pixel();
+            let bpp = out_format.bytes_per_pixel();
             if bpp > 1 {
-                assert_eq!(0, dst_len % bpp, "Output [u8] slice's length {dst_len} is not a multiple of {bpp}");
+                if dst_len % bpp != 0 {
+                    return Err(PixelFormatError::PartialPixel { input: false, len: dst_len, bytes_per_pixel: bpp });
+                }
                 dst_len /= bpp;
             }
         }
-        src_len.min(dst_len).min(u32::MAX as usize) as u32
+        Ok(src_len.min(dst_len))
     }
 
     /// This function translates bitmaps according of parameters setup when creating the color transform.
@@ -239,6 +403,20 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
         }
     }
 
+    /// Same as `transform_pixels()`, but returns `Error::PixelFormatMismatch` instead of panicking
+    /// if a `[u8]` slice has a partial pixel, or the formats are planar.
+    #[inline]
+    pub fn try_transform_pixels(&self, src: &[InputPixelFormat], dst: &mut [OutputPixelFormat]) -> LCMSResult<()> {
+        let num_pixels = self.try_num_pixels(src.len(), dst.len())?;
+        unsafe {
+            ffi::cmsDoTransform(self.handle,
+                                src.as_ptr().cast::<c_void>(),
+                                dst.as_mut_ptr().cast::<c_void>(),
+                                num_pixels);
+        }
+        Ok(())
+    }
+
     /// This function translates bitmaps according of parameters setup when creating the color transform.
     ///
     /// It allows destination to be uninitailized, and returns the same slice, initialized.
@@ -262,6 +440,64 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
         }
     }
 
+    /// Translates a 2D image that may have padding between rows, e.g. a sub-rectangle of a larger bitmap or a frame buffer with a pitch.
+    ///
+    /// Strides are distances between starts of consecutive rows, counted in slice elements (pixels, or bytes when `[u8]` is used).
+    /// The input and output can have different strides. The last row doesn't need to be padded.
+    ///
+    /// # Panics
+    ///
+    /// If a stride is smaller than the width, or a slice is too short to hold `height` rows.
+    #[track_caller]
+    pub fn transform_image(&self, src: &[InputPixelFormat], src_stride: usize, dst: &mut [OutputPixelFormat], dst_stride: usize, width: usize, height: usize) {
+        let src_line_bytes = image_line_bytes::<InputPixelFormat>(self.input_pixel_format(), src.len(), src_stride, width, height, "Input");
+        let dst_line_bytes = image_line_bytes::<OutputPixelFormat>(self.output_pixel_format(), dst.len(), dst_stride, width, height, "Output");
+        if width == 0 || height == 0 {
+            return;
+        }
+        unsafe {
+            ffi::cmsDoTransformLineStride(self.handle,
+                                          src.as_ptr().cast::<c_void>(),
+                                          dst.as_mut_ptr().cast::<c_void>(),
+                                          width as u32, height as u32,
+                                          src_line_bytes, dst_line_bytes,
+                                          src_line_bytes, dst_line_bytes);
+        }
+    }
+
+    /// Translates planar bitmaps, where every channel is stored in a separate plane.
+    ///
+    /// The planes are stored one after another in the same slice, and each plane starts `src_plane_len`/`dst_plane_len` elements after the previous one.
+    /// The plane length can be larger than `pixels` if there's padding between the planes.
+    ///
+    /// The slices contain individual samples rather than whole pixels, e.g. `u16` for `PixelFormat::RGB_16_PLANAR`.
+    /// `[u8]` slices are allowed for any format, and then the plane lengths are in bytes.
+    ///
+    /// One side can use a packed (non-planar) format, e.g. to convert planar YUV to packed RGB.
+    /// Its slice contains whole pixels like in `transform_pixels()`, and its plane length is ignored.
+    ///
+    /// # Panics
+    ///
+    /// If neither format is planar, or the slices are too short to hold all planes.
+    #[track_caller]
+    pub fn transform_planar(&self, src: &[InputPixelFormat], src_plane_len: usize, dst: &mut [OutputPixelFormat], dst_plane_len: usize, pixels: usize) {
+        let (in_format, out_format) = (self.input_pixel_format(), self.output_pixel_format());
+        assert!(in_format.planar() || out_format.planar(), "Neither {in_format:?} nor {out_format:?} is planar. Use transform_pixels()");
+        let src_plane_bytes = planar_bytes::<InputPixelFormat>(in_format, src.len(), src_plane_len, pixels, "Input");
+        let dst_plane_bytes = planar_bytes::<OutputPixelFormat>(out_format, dst.len(), dst_plane_len, pixels, "Output");
+        if pixels == 0 {
+            return;
+        }
+        unsafe {
+            ffi::cmsDoTransformLineStride(self.handle,
+                                          src.as_ptr().cast::<c_void>(),
+                                          dst.as_mut_ptr().cast::<c_void>(),
+                                          pixels as u32, 1,
+                                          src_plane_bytes, dst_plane_bytes,
+                                          src_plane_bytes, dst_plane_bytes);
+        }
+    }
+
     #[inline]
     #[track_caller]
     pub fn new_flags_context(context: impl AsRef<Ctx>, input: &Profile<Ctx>, in_format: PixelFormat,
@@ -269,13 +505,23 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
                              intent: Intent, flags: Flags<Fl>)
                              -> LCMSResult<Self> {
         Self::check_formats(in_format, out_format);
-        unsafe {
-            Self::new_handle(ffi::cmsCreateTransformTHR(context.as_ref().as_ptr(),
+        Self::try_new_flags_context(context, input, in_format, output, out_format, intent, flags)
+    }
+
+    /// Same as `new_flags_context()`, but returns `Error::PixelFormatMismatch` instead of panicking if the pixel types don't match the formats.
+    #[inline]
+    pub fn try_new_flags_context(context: impl AsRef<Ctx>, input: &Profile<Ctx>, in_format: PixelFormat,
+                                 output: &Profile<Ctx>, out_format: PixelFormat,
+                                 intent: Intent, flags: Flags<Fl>)
+                                 -> LCMSResult<Self> {
+        Self::try_check_formats(in_format, out_format)?;
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Transform::new_flags_context", || unsafe {
+            Self::new_handle(ffi::cmsCreateTransformTHR(context,
                 input.handle, in_format,
                 output.handle, out_format,
                 intent, flags.bits()))
-        }
-
+        })
     }
 
     #[inline]
@@ -286,11 +532,23 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
                         flags: Flags<Fl>)
                         -> LCMSResult<Self> {
         Self::check_formats(in_format, out_format);
-        unsafe {
-            Self::new_handle(ffi::cmsCreateProofingTransformTHR(context.as_ref().as_ptr(), input.handle, in_format,
+        Self::try_new_proofing_context(context, input, in_format, output, out_format, proofing, intent, proofng_intent, flags)
+    }
+
+    /// Same as `new_proofing_context()`, but returns `Error::PixelFormatMismatch` instead of panicking if the pixel types don't match the formats.
+    #[inline]
+    pub fn try_new_proofing_context(context: impl AsRef<Ctx>, input: &Profile<Ctx>, in_format: PixelFormat,
+                                    output: &Profile<Ctx>, out_format: PixelFormat,
+                                    proofing: &Profile<Ctx>, intent: Intent, proofng_intent: Intent,
+                                    flags: Flags<Fl>)
+                                    -> LCMSResult<Self> {
+        Self::try_check_formats(in_format, out_format)?;
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Transform::new_proofing_context", || unsafe {
+            Self::new_handle(ffi::cmsCreateProofingTransformTHR(context, input.handle, in_format,
                 output.handle, out_format,
                 proofing.handle, intent, proofng_intent, flags.bits()))
-        }
+        })
     }
 
     #[inline]
@@ -298,12 +556,21 @@ impl<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod, Ctx: Context,
     pub fn new_multiprofile_context(context: impl AsRef<Ctx>, profiles: &[&Profile<Ctx>],
                                 in_format: PixelFormat, out_format: PixelFormat, intent: Intent, flags: Flags<Fl>) -> LCMSResult<Self> {
         Self::check_formats(in_format, out_format);
+        Self::try_new_multiprofile_context(context, profiles, in_format, out_format, intent, flags)
+    }
+
+    /// Same as `new_multiprofile_context()`, but returns `Error::PixelFormatMismatch` instead of panicking if the pixel types don't match the formats.
+    #[inline]
+    pub fn try_new_multiprofile_context(context: impl AsRef<Ctx>, profiles: &[&Profile<Ctx>],
+                                        in_format: PixelFormat, out_format: PixelFormat, intent: Intent, flags: Flags<Fl>) -> LCMSResult<Self> {
+        Self::try_check_formats(in_format, out_format)?;
         let mut handles: Vec<_> = profiles.iter().map(|p| p.handle).collect();
-        unsafe {
+        let context = context.as_ref().as_ptr();
+        capture_errors(context, "Transform::new_multiprofile_context", || unsafe {
             Self::new_handle(
-                ffi::cmsCreateMultiprofileTransformTHR(context.as_ref().as_ptr(), handles.as_mut_ptr(), handles.len() as u32, in_format, out_format, intent, flags.bits()),
+                ffi::cmsCreateMultiprofileTransformTHR(context, handles.as_mut_ptr(), handles.len() as u32, in_format, out_format, intent, flags.bits()),
             )
-        }
+        })
     }
 }
 
@@ -366,7 +633,78 @@ impl<F, T, L> Transform<F, T, GlobalContext, L> {
     }
 }
 
-fn is_u8<P: 'static>() -> bool {
+/// Validates a strided image buffer and returns the number of bytes per line
+#[track_caller]
+fn image_line_bytes<P>(format: PixelFormat, len: usize, stride: usize, width: usize, height: usize, io: &str) -> u32 {
+    assert!(!format.planar(), "{io} format {format:?} is planar. Use transform_planar()");
+    let elem_size = std::mem::size_of::<P>();
+    let overflow = || -> ! { panic!("{io} image is too large") };
+    let stride_bytes = stride.checked_mul(elem_size).unwrap_or_else(|| overflow());
+    let row_bytes = width.checked_mul(format.bytes_per_pixel()).unwrap_or_else(|| overflow());
+    assert!(row_bytes <= stride_bytes, "{io} stride {stride} is smaller than the width {width}");
+    if height > 0 {
+        let needed_bytes = (height - 1).checked_mul(stride_bytes)
+            .and_then(|b| b.checked_add(row_bytes))
+            .unwrap_or_else(|| overflow());
+        // can't overflow for an existing slice
+        assert!(needed_bytes <= len * elem_size, "{io} slice has {len} elements, but {height} rows with stride {stride} need {}", (needed_bytes + elem_size - 1) / elem_size);
+    }
+    assert!(width <= u32::MAX as usize && height <= u32::MAX as usize);
+    u32::try_from(stride_bytes).unwrap_or_else(|_| overflow())
+}
+
+/// Validates a planar buffer and returns the number of bytes per plane
+#[track_caller]
+fn planar_bytes<P>(format: PixelFormat, len: usize, plane_len: usize, pixels: usize, io: &str) -> u32 {
+    let elem_size = std::mem::size_of::<P>();
+    let overflow = || -> ! { panic!("{io} planes are too large") };
+    let slice_bytes = len * elem_size; // can't overflow for an existing slice
+    if !format.planar() {
+        // LCMS uses the plane size as the size of the packed line
+        let line_bytes = pixels.checked_mul(format.bytes_per_pixel()).unwrap_or_else(|| overflow());
+        assert!(line_bytes <= slice_bytes, "{io} slice has {len} elements, but {pixels} pixels of {format:?} need {}", (line_bytes + elem_size - 1) / elem_size);
+        return u32::try_from(line_bytes).unwrap_or_else(|_| overflow());
+    }
+    let plane_bytes = plane_len.checked_mul(elem_size).unwrap_or_else(|| overflow());
+    let line_bytes = pixels.checked_mul(format.bytes_per_channel()).unwrap_or_else(|| overflow());
+    assert!(line_bytes <= plane_bytes, "{io} plane length {plane_len} is too short for {pixels} pixels");
+    let planes = format.channels() + format.extra();
+    let needed_bytes = planes.saturating_sub(1).checked_mul(plane_bytes)
+        .and_then(|b| b.checked_add(line_bytes))
+        .unwrap_or_else(|| overflow());
+    assert!(needed_bytes <= slice_bytes, "{io} slice has {len} elements, but {planes} planes of {plane_len} need {}", (needed_bytes + elem_size - 1) / elem_size);
+    assert!(pixels <= u32::MAX as usize);
+    u32::try_from(plane_bytes).unwrap_or_else(|_| overflow())
+}
+
+/// Reason why a pixel type or a slice can't be used with a `PixelFormat`. See `Error::PixelFormatMismatch`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PixelFormatError {
+    /// Size of the pixel type isn't the size of a pixel in the format. `input` is false for the output format.
+    BytesPerPixel { format: PixelFormat, input: bool, expected: usize, actual: usize },
+    /// Planar formats use the type of a single sample, and its size isn't the size of a sample in the format
+    BytesPerSample { format: PixelFormat, input: bool, expected: usize, actual: usize },
+    /// Length of a `[u8]` slice isn't a multiple of the pixel size
+    PartialPixel { input: bool, len: usize, bytes_per_pixel: usize },
+    /// Planar formats can only be used with `transform_planar()`
+    PlanarUnsupported { in_format: PixelFormat, out_format: PixelFormat },
+}
+
+impl fmt::Display for PixelFormatError {
+    #[cold]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let io = |input: bool| if input { "input" } else { "output" };
+        match *self {
+            Self::BytesPerPixel { format, input, expected, actual } => write!(f, "{format:?} has {expected} bytes per pixel, but the {} format has {actual}", io(input)),
+            Self::BytesPerSample { format, input, expected, actual } => write!(f, "Planar {format:?} has {expected} bytes per sample, but the {} format has {actual}", io(input)),
+            Self::PartialPixel { input, len, bytes_per_pixel } => write!(f, "{} [u8] slice's length {len} is not a multiple of {bytes_per_pixel}", if input { "Input" } else { "Output" }),
+            Self::PlanarUnsupported { in_format, out_format } => write!(f, "Planar {in_format:?}/{out_format:?} formats require transform_planar()"),
+        }
+    }
+}
+
+pub(crate) fn is_u8<P: 'static>() -> bool {
     std::mem::size_of::<P>() == 1 && std::mem::align_of::<P>() == 1 && std::any::TypeId::of::<P>() == std::any::TypeId::of::<u8>()
 }
 
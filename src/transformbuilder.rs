@@ -0,0 +1,310 @@
+use crate::context::{capture_errors, Context};
+use crate::*;
+use std::ptr;
+
+/// Creates a `Transform` from a chain of profiles. Alternative to `Transform::new*` constructors.
+///
+/// ```rust,ignore
+/// let t: Transform<[u8; 3], [u8; 3], _, _> = TransformBuilder::new(&[&input, &output], PixelFormat::RGB_8, PixelFormat::RGB_8)
+///     .intent(Intent::RelativeColorimetric)
+///     .proofing(&printer, Intent::AbsoluteColorimetric)
+///     .flags(Flags::SOFT_PROOFING | Flags::NO_CACHE)
+///     .build()?;
+/// ```
+///
+/// It can also set a separate rendering intent, black point compensation and adaptation state for each profile (`cmsCreateExtendedTransform`):
+///
+/// ```rust,ignore
+/// let t: Transform<[u8; 3], [u8; 4]> = TransformBuilder::new(&[&srgb, &lab, &printer], PixelFormat::RGB_8, PixelFormat::CMYK_8)
+///     .intents(&[Intent::Perceptual, Intent::RelativeColorimetric, Intent::RelativeColorimetric])
+///     .black_point_compensation(&[false, true, true])
+///     .build()?;
+/// ```
+///
+/// Arrays that aren't set use the same defaults as `Transform::new_multiprofile()`.
+/// Lengths of the arrays and sizes of the pixel types are checked when the transform is built.
+pub struct TransformBuilder<'a, Ctx: Context = GlobalContext, Fl: CacheFlag = AllowCache> {
+    profiles: Vec<&'a Profile<Ctx>>,
+    in_format: PixelFormat,
+    out_format: PixelFormat,
+    intent: Intent,
+    proofing: Option<(&'a Profile<Ctx>, Intent)>,
+    intents: Option<Vec<Intent>>,
+    black_point_compensation: Option<Vec<bool>>,
+    adaptation_states: Option<Vec<f64>>,
+    gamut_check: Option<(&'a Profile<Ctx>, usize)>,
+    flags: Flags<Fl>,
+}
+
+impl<'a, Ctx: Context> TransformBuilder<'a, Ctx, AllowCache> {
+    /// Profiles are applied in order, from the input to the output, e.g. `&[&input, &output]`. LCMS supports up to 255 profiles.
+    ///
+    /// The transform uses the context of the first profile. Use `Profile::new_*_context()` to create a thread-safe transform.
+    #[must_use]
+    pub fn new(profiles: &[&'a Profile<Ctx>], in_format: PixelFormat, out_format: PixelFormat) -> Self {
+        Self {
+            profiles: profiles.to_vec(),
+            in_format,
+            out_format,
+            intent: Intent::Perceptual,
+            proofing: None,
+            intents: None,
+            black_point_compensation: None,
+            adaptation_states: None,
+            gamut_check: None,
+            flags: Flags::default(),
+        }
+    }
+}
+
+impl<'a, Ctx: Context, Fl: CacheFlag> TransformBuilder<'a, Ctx, Fl> {
+    /// Rendering intent used for all profiles. The default is `Intent::Perceptual`.
+    #[must_use]
+    pub fn intent(mut self, intent: Intent) -> Self {
+        self.intent = intent;
+        self
+    }
+
+    /// Emulates how the colors would look on the `proofing` device. Set `Flags::SOFT_PROOFING` and/or `Flags::GAMUT_CHECK` too.
+    ///
+    /// `proofing_intent` is used for converting to the proofing device. See `Transform::new_proofing()`.
+    /// Needs exactly two profiles, and can't be combined with per-profile settings.
+    #[must_use]
+    pub fn proofing(mut self, proofing: &'a Profile<Ctx>, proofing_intent: Intent) -> Self {
+        self.proofing = Some((proofing, proofing_intent));
+        self
+    }
+
+    /// Rendering intent for each profile. Overrides `intent()`.
+    #[must_use]
+    pub fn intents(mut self, intents: &[Intent]) -> Self {
+        self.intents = Some(intents.to_vec());
+        self
+    }
+
+    /// Black point compensation for each profile. By default it's enabled for all if flags include `Flags::BLACKPOINT_COMPENSATION`.
+    #[must_use]
+    pub fn black_point_compensation(mut self, enabled: &[bool]) -> Self {
+        self.black_point_compensation = Some(enabled.to_vec());
+        self
+    }
+
+    /// Adaptation state for absolute colorimetric intent for each profile (0=Not adapted, 1=Complete adaptation).
+    /// By default all use the context's `adaptation_state()`.
+    #[must_use]
+    pub fn adaptation_states(mut self, states: &[f64]) -> Self {
+        self.adaptation_states = Some(states.to_vec());
+        self
+    }
+
+    /// Marks colors that are out of gamut of the `gamut` profile with the context's alarm codes. Enables `Flags::GAMUT_CHECK`.
+    ///
+    /// `pcs_position` is the number of profiles before the PCS at which the check is done.
+    /// It must be in `1..n` for a chain of `n` profiles, e.g. 1 for the PCS between the input and the output profile.
+    #[must_use]
+    pub fn gamut_check(mut self, gamut: &'a Profile<Ctx>, pcs_position: usize) -> Self {
+        self.gamut_check = Some((gamut, pcs_position));
+        self
+    }
+
+    /// Replaces flags. Use `Flags::NO_CACHE` to make the transform `Sync`.
+    ///
+    /// The flags decide whether the built `Transform` has `AllowCache` or `DisallowCache` type.
+    #[must_use]
+    pub fn flags<NewFl: CacheFlag>(self, flags: Flags<NewFl>) -> TransformBuilder<'a, Ctx, NewFl> {
+        TransformBuilder {
+            profiles: self.profiles,
+            in_format: self.in_format,
+            out_format: self.out_format,
+            intent: self.intent,
+            proofing: self.proofing,
+            intents: self.intents,
+            black_point_compensation: self.black_point_compensation,
+            adaptation_states: self.adaptation_states,
+            gamut_check: self.gamut_check,
+            flags,
+        }
+    }
+
+    /// Checks the settings, and creates the transform with the LCMS function that supports them.
+    ///
+    /// Pixel types have the same requirements as in `Transform::new()`, but mismatches are reported as `Error::PixelFormatMismatch` instead of panicking.
+    pub fn build<InputPixelFormat: Copy + Pod, OutputPixelFormat: Copy + Pod>(self) -> LCMSResult<Transform<InputPixelFormat, OutputPixelFormat, Ctx, Fl>> {
+        Transform::<InputPixelFormat, OutputPixelFormat, Ctx, Fl>::try_check_formats(self.in_format, self.out_format)?;
+        let n = self.profiles.len();
+        if n == 0 || n > 255 {
+            return Err(bad_parameter(format!("{n} profiles given, 1..=255 expected")));
+        }
+        for (name, len) in [
+            ("intents", self.intents.as_ref().map(Vec::len)),
+            ("black point compensation", self.black_point_compensation.as_ref().map(Vec::len)),
+            ("adaptation states", self.adaptation_states.as_ref().map(Vec::len)),
+        ] {
+            match len {
+                Some(len) if len != n => return Err(bad_parameter(format!("{len} {name} given for {n} profiles"))),
+                _ => {},
+            }
+        }
+        if let Some((_, pos)) = self.gamut_check {
+            if pos == 0 || pos >= n {
+                return Err(bad_parameter(format!("gamut check position {pos} is not after the first and before the last of {n} profiles")));
+            }
+        }
+        let extended = self.intents.is_some() || self.black_point_compensation.is_some() || self.adaptation_states.is_some() || self.gamut_check.is_some();
+
+        let context = unsafe { ffi::cmsGetProfileContextID(self.profiles[0].handle) };
+        let mut handles: Vec<_> = self.profiles.iter().map(|p| p.handle).collect();
+        let flags = self.flags.bits();
+        let (in_format, out_format, intent) = (self.in_format, self.out_format, self.intent);
+
+        if let Some((proofing, proofing_intent)) = self.proofing {
+            if n != 2 || extended {
+                return Err(bad_parameter("proofing needs exactly 2 profiles, and no per-profile settings".into()));
+            }
+            return capture_errors(context, "TransformBuilder::build", || unsafe {
+                Transform::new_handle(ffi::cmsCreateProofingTransformTHR(context, handles[0], in_format, handles[1], out_format,
+                    proofing.handle, intent, proofing_intent, flags))
+            });
+        }
+        if !extended {
+            return capture_errors(context, "TransformBuilder::build", || unsafe {
+                Transform::new_handle(if n == 2 {
+                    ffi::cmsCreateTransformTHR(context, handles[0], in_format, handles[1], out_format, intent, flags)
+                } else {
+                    ffi::cmsCreateMultiprofileTransformTHR(context, handles.as_mut_ptr(), n as u32, in_format, out_format, intent, flags)
+                })
+            });
+        }
+
+        let bpc_default = self.flags.has(Flags::BLACKPOINT_COMPENSATION);
+        let adaptation_default = unsafe { ffi::cmsSetAdaptationStateTHR(context, -1.) };
+        let mut intents: Vec<u32> = match self.intents {
+            Some(intents) => intents.into_iter().map(|i| i as u32).collect(),
+            None => vec![intent as u32; n],
+        };
+        let mut bpc: Vec<ffi::Bool> = match self.black_point_compensation {
+            Some(bpc) => bpc.into_iter().map(ffi::Bool::from).collect(),
+            None => vec![bpc_default.into(); n],
+        };
+        let mut adaptation_states = self.adaptation_states.unwrap_or_else(|| vec![adaptation_default; n]);
+        let (gamut, gamut_position, flags) = match self.gamut_check {
+            Some((gamut, pos)) => (gamut.handle, pos as u32, flags | ffi::FLAGS_GAMUTCHECK),
+            None => (ptr::null_mut(), 0, flags),
+        };
+
+        // LCMS wants the check before the last profile. A Lab identity at the PCS doesn't change colors,
+        // and the gamut check only uses profiles before its position.
+        let mut identity = None;
+        if gamut_position as usize == n - 1 {
+            let pos = n - 1;
+            let lab = identity.insert(capture_errors(context, "TransformBuilder::build", || {
+                Profile::<Ctx>::new_handle(unsafe { ffi::cmsCreateLab4ProfileTHR(context, ptr::null()) })
+            })?);
+            handles.insert(pos, lab.handle);
+            intents.insert(pos, intents[pos]);
+            bpc.insert(pos, bpc[pos]);
+            adaptation_states.insert(pos, adaptation_states[pos]);
+        }
+
+        capture_errors(context, "TransformBuilder::build", || unsafe {
+            Transform::new_handle(ffi::cmsCreateExtendedTransform(context, handles.len() as u32, handles.as_mut_ptr(),
+                bpc.as_mut_ptr(), intents.as_mut_ptr(), adaptation_states.as_mut_ptr(),
+                gamut, gamut_position,
+                in_format, out_format, flags))
+        })
+    }
+}
+
+fn bad_parameter(message: String) -> Error {
+    Error::BadParameter(ErrorDetails { code: ErrorCode::Range, message, function: "TransformBuilder::build" })
+}
+
+#[test]
+fn extended_transform() {
+    let srgb = Profile::new_srgb();
+    let lab = Profile::new_lab4_context(GlobalContext::new(), &white_point_from_temp(6504.).unwrap()).unwrap();
+    let gray = Profile::new_gray(&white_point_from_temp(6504.).unwrap(), &ToneCurve::new(2.2)).unwrap();
+
+    let t: Transform<[u8; 3], [u8; 3], _, _> = TransformBuilder::new(&[&srgb, &lab, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .intents(&[Intent::Perceptual, Intent::RelativeColorimetric, Intent::AbsoluteColorimetric])
+        .black_point_compensation(&[true, false, false])
+        .adaptation_states(&[1., 1., 0.5])
+        .flags(Flags::NO_CACHE)
+        .build().unwrap();
+    let mut px = [[10u8, 200, 30]];
+    t.transform_in_place(&mut px);
+    assert!(px[0][1] > 150);
+
+    // everything saturated is out of gray's gamut
+    let t: Transform<[u8; 3], [u8; 3]> = TransformBuilder::new(&[&srgb, &lab, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .gamut_check(&gray, 1)
+        .build().unwrap();
+    let mut px = [[255u8, 0, 0]];
+    t.transform_in_place(&mut px);
+    assert_eq!([0x7F, 0x7F, 0x7F], px[0]);
+    let t: Transform<[u8; 3], [u8; 3]> = TransformBuilder::new(&[&srgb, &lab, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .gamut_check(&srgb, 1)
+        .build().unwrap();
+    let mut px = [[255u8, 0, 0]];
+    t.transform_in_place(&mut px);
+    assert_eq!([255, 0, 0], px[0]);
+
+    let err = TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .intents(&[Intent::Perceptual])
+        .build::<[u8; 3], [u8; 3]>().unwrap_err();
+    assert!(matches!(err, Error::BadParameter(ErrorDetails { code: ErrorCode::Range, .. })));
+    assert!(TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .gamut_check(&gray, 2).build::<[u8; 3], [u8; 3]>().is_err());
+
+    let t: Transform<[u8; 3], [u8; 3]> = TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .gamut_check(&gray, 1)
+        .build().unwrap();
+    let mut px = [[255u8, 0, 0], [10, 100, 200]];
+    t.transform_in_place(&mut px);
+    assert_eq!([0x7F, 0x7F, 0x7F], px[0]);
+    let t: Transform<[u8; 3], [u8; 3]> = TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .gamut_check(&srgb, 1)
+        .build().unwrap();
+    let mut px = [[255u8, 0, 0], [10, 100, 200]];
+    t.transform_in_place(&mut px);
+    assert_eq!([[255, 0, 0], [10, 100, 200]], px);
+    assert!(TransformBuilder::<GlobalContext>::new(&[], PixelFormat::RGB_8, PixelFormat::RGB_8).build::<[u8; 3], [u8; 3]>().is_err());
+}
+
+#[test]
+fn transform_builder() {
+    fn is_sync<T: Sync>(_: &T) {}
+
+    let srgb = Profile::new_srgb();
+    let lab = Profile::new_lab4_context(GlobalContext::new(), &white_point_from_temp(5000.).unwrap()).unwrap();
+
+    let t = TransformBuilder::new(&[&srgb, &lab], PixelFormat::RGB_8, PixelFormat::Lab_DBL)
+        .intent(Intent::RelativeColorimetric)
+        .flags(Flags::NO_CACHE)
+        .build().unwrap();
+    is_sync(&t);
+    let mut out = [[0f64; 3]];
+    t.transform_pixels(&[[255u8, 255, 255]], &mut out);
+    assert!((out[0][0] - 100.).abs() < 0.1);
+
+    let multi: Transform<[u8; 3], [u8; 3]> = TransformBuilder::new(&[&srgb, &lab, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8).build().unwrap();
+    let mut px = [[12u8, 34, 56]];
+    multi.transform_in_place(&mut px);
+    assert_eq!([12, 34, 56], px[0]);
+
+    let proof: Transform<[u8; 3], [u8; 3]> = TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .proofing(&lab, Intent::AbsoluteColorimetric)
+        .flags(Flags::SOFT_PROOFING)
+        .build().unwrap();
+    let mut px = [[0u8, 0, 0]];
+    proof.transform_in_place(&mut px);
+    assert_eq!([0, 0, 0], px[0]);
+    assert!(TransformBuilder::new(&[&srgb, &lab, &srgb], PixelFormat::RGB_8, PixelFormat::RGB_8)
+        .proofing(&lab, Intent::Perceptual).build::<[u8; 3], [u8; 3]>().is_err());
+
+    let err = TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8, PixelFormat::RGBA_8).build::<[u8; 3], [u8; 3]>().unwrap_err();
+    assert_eq!(Error::PixelFormatMismatch(PixelFormatError::BytesPerPixel { format: PixelFormat::RGBA_8, input: false, expected: 4, actual: 3 }), err);
+    assert!(err.to_string().contains("output"));
+    assert!(matches!(TransformBuilder::new(&[&srgb, &srgb], PixelFormat::RGB_8_PLANAR, PixelFormat::RGB_8_PLANAR).build::<u16, u8>(),
+        Err(Error::PixelFormatMismatch(PixelFormatError::BytesPerSample { input: true, .. }))));
+}
@@ -158,3 +158,222 @@ fn context() {
     let tmp = [0u8,0u8,0u8];
     t.transform_in_place(&mut [tmp]);
 }
+
+#[test]
+fn planar() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8_PLANAR, &srgb, PixelFormat::BGR_8_PLANAR, Intent::Perceptual).unwrap();
+    // two pixels per plane, with one byte of padding after each plane
+    let src = [255u8, 0, 99, 0, 255, 99, 0, 0, 99];
+    let mut dst = [1u8; 8];
+    tr.transform_planar(&src, 3, &mut dst, 3, 2);
+    assert_eq!(dst, [0, 0, 1, 0, 255, 1, 255, 0]);
+
+    let tr = Transform::new(&srgb, PixelFormat::RGB_16_PLANAR, &srgb, PixelFormat::RGB_16_PLANAR, Intent::Perceptual).unwrap();
+    let src = [0xFFFFu16, 0, 0, 0xFFFF, 0, 0];
+    let mut dst = [1u16; 6];
+    tr.transform_planar(&src, 2, &mut dst, 2, 2);
+    assert_eq!(src, dst);
+
+    // planar to packed and back
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8_PLANAR, &srgb, PixelFormat::RGB_8, Intent::Perceptual).unwrap();
+    let src = [255u8, 0, 99, 0, 255, 99, 0, 0, 99];
+    let mut dst = [[1u8; 3]; 2];
+    tr.transform_planar(&src, 3, &mut dst, 0, 2);
+    assert_eq!(dst, [[255, 0, 0], [0, 255, 0]]);
+    let tr = Transform::new(&srgb, PixelFormat::BGR_8, &srgb, PixelFormat::RGB_8_PLANAR, Intent::Perceptual).unwrap();
+    let mut planes = [1u8; 6];
+    tr.transform_planar(&dst, 0, &mut planes, 2, 2);
+    assert_eq!(planes, [0, 0, 0, 255, 255, 0]);
+}
+
+#[test]
+#[should_panic]
+fn planar_packed_too_short() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8_PLANAR, &srgb, PixelFormat::RGB_8, Intent::Perceptual).unwrap();
+    tr.transform_planar(&[0u8; 9], 3, &mut [[0u8; 3]; 1], 0, 2);
+}
+
+#[test]
+#[should_panic]
+fn planar_pixels() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8_PLANAR, &srgb, PixelFormat::RGB_8_PLANAR, Intent::Perceptual).unwrap();
+    tr.transform_pixels(&[0u8; 6], &mut [0u8; 6]);
+}
+
+#[test]
+fn strided_image() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8, &srgb, PixelFormat::BGR_8, Intent::Perceptual).unwrap();
+    // 2x2 image in a 3-pixel-wide buffer
+    let src = [
+        [255u8, 0, 0], [0, 255, 0], [7, 7, 7],
+        [0, 0, 255], [255, 255, 255],
+    ];
+    let mut dst = [[1u8; 3]; 4];
+    tr.transform_image(&src, 3, &mut dst, 2, 2, 2);
+    assert_eq!(dst, [[0, 0, 255], [0, 255, 0], [255, 0, 0], [255, 255, 255]]);
+
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8, &srgb, PixelFormat::BGR_8, Intent::Perceptual).unwrap();
+    let mut dst_bytes = [1u8; 2 * 7];
+    tr.transform_image(&src, 3, &mut dst_bytes, 7, 2, 2);
+    assert_eq!(dst_bytes, [0, 0, 255, 0, 255, 0, 1, 255, 0, 0, 255, 255, 255, 1]);
+}
+
+#[test]
+#[should_panic]
+fn strided_image_too_short() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8, &srgb, PixelFormat::RGB_8, Intent::Perceptual).unwrap();
+    tr.transform_image(&[[0u8; 3]; 5], 3, &mut [[0u8; 3]; 6], 3, 3, 2);
+}
+
+#[test]
+#[should_panic(expected = "too large")]
+fn strided_image_overflow() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_8, &srgb, PixelFormat::RGB_8, Intent::Perceptual).unwrap();
+    tr.transform_image(&[[0u8; 3]; 5], usize::MAX / 2, &mut [[0u8; 3]; 6], 3, 1, 2);
+}
+
+#[test]
+fn try_transform() {
+    let srgb = Profile::new_srgb();
+    let err = Transform::<[u8; 3], [u8; 3]>::try_new(&srgb, PixelFormat::RGB_16, &srgb, PixelFormat::RGB_8, Intent::Perceptual).unwrap_err();
+    assert_eq!(Error::PixelFormatMismatch(PixelFormatError::BytesPerPixel { format: PixelFormat::RGB_16, input: true, expected: 6, actual: 3 }), err);
+    assert!(Transform::<RGB16, [u8; 3]>::try_new_multiprofile(&[&srgb, &srgb], PixelFormat::RGB_16, PixelFormat::RGB_8, Intent::Perceptual, Flags::default()).is_ok());
+
+    let tr = Transform::<u8, u8>::try_new(&srgb, PixelFormat::RGB_8, &srgb, PixelFormat::RGBA_8, Intent::Perceptual).unwrap();
+    let mut dst = [0u8; 8];
+    tr.try_transform_pixels(&[1, 2, 3, 4, 5, 6], &mut dst).unwrap();
+    assert_eq!([1, 2, 3, 0, 4, 5, 6, 0], dst);
+    assert_eq!(Err(Error::PixelFormatMismatch(PixelFormatError::PartialPixel { input: true, len: 5, bytes_per_pixel: 3 })), tr.try_transform_pixels(&[0; 5], &mut dst));
+    assert!(matches!(tr.try_transform_pixels(&[0; 6], &mut [0; 7]), Err(Error::PixelFormatMismatch(PixelFormatError::PartialPixel { input: false, .. }))));
+
+    let tr = Transform::<u8, u8>::try_new(&srgb, PixelFormat::RGB_8, &srgb, PixelFormat::RGB_8, Intent::Perceptual).unwrap();
+    let mut px = [10u8, 20, 30, 40];
+    assert!(tr.try_transform_in_place(&mut px).is_err());
+    tr.try_transform_in_place(&mut px[..3]).unwrap();
+
+    let tr = Transform::<u8, u8>::try_new(&srgb, PixelFormat::RGB_8_PLANAR, &srgb, PixelFormat::RGB_8_PLANAR, Intent::Perceptual).unwrap();
+    let err = tr.try_transform_pixels(&[0; 3], &mut [0; 3]).unwrap_err();
+    assert!(matches!(err, Error::PixelFormatMismatch(PixelFormatError::PlanarUnsupported { .. })));
+    assert!(err.to_string().contains("transform_planar"));
+}
+
+#[test]
+fn with_formats() {
+    let srgb = Profile::new_srgb();
+    let gray = Profile::new_icc(SGRAY_PROFILE).unwrap();
+    let tr = Transform::new(&srgb, PixelFormat::RGB_16, &gray, PixelFormat::GRAY_16, Intent::Perceptual).unwrap();
+    let mut out = [0u16; 1];
+    tr.transform_pixels(&[RGB16 { r: 0xFFFF, g: 0xFFFF, b: 0xFFFF }], &mut out);
+    assert_eq!(0xFFFF, out[0]);
+
+    let tr = tr.with_formats::<[u16; 4], u8>(PixelFormat::BGRA_16, PixelFormat::GRAY_8).unwrap();
+    let mut out = [0u8; 2];
+    tr.transform_pixels(&[[0xFFFF, 0xFFFF, 0xFFFF, 0], [0, 0, 0, 0xFFFF]], &mut out);
+    assert_eq!([255, 0], out);
+
+    let (tr, err) = tr.with_formats::<[u16; 3], [u16; 3]>(PixelFormat::RGB_16, PixelFormat::GRAY_16).unwrap_err();
+    assert!(matches!(err, Error::PixelFormatMismatch(PixelFormatError::BytesPerPixel { input: false, .. })));
+    assert_eq!(PixelFormat::BGRA_16, tr.input_format());
+    tr.transform_pixels(&[[0xFFFF, 0xFFFF, 0xFFFF, 0]], &mut out[..1]);
+    assert_eq!(255, out[0]);
+
+    let tr = Transform::<[u8; 3], u8>::new(&srgb, PixelFormat::RGB_8, &gray, PixelFormat::GRAY_8, Intent::Perceptual).unwrap();
+    let (tr, _) = tr.with_formats::<[u16; 3], u16>(PixelFormat::RGB_16, PixelFormat::GRAY_16).unwrap_err();
+    let mut out = [0u8; 1];
+    tr.transform_pixels(&[[255, 255, 255]], &mut out);
+    assert_eq!(255, out[0]);
+}
+
+impl_lcms_pixel!(RGB16 => PixelFormat::RGB_16);
+
+#[test]
+fn new_typed() {
+    let srgb = Profile::new_srgb();
+    let tr = Transform::<RGB16, [u8; 4]>::new_typed(&srgb, &srgb, Intent::Perceptual).unwrap();
+    assert_eq!(PixelFormat::RGB_16, tr.input_format());
+    assert_eq!(PixelFormat::RGBA_8, tr.output_format());
+    let mut dst = [[0u8; 4]];
+    tr.transform_pixels(&[RGB16 { r: 0xFFFF, g: 0, b: 0 }], &mut dst);
+    assert_eq!([255, 0, 0, 0], dst[0]);
+
+    let tr = Transform::<[u8; 3], [u8; 3]>::new_typed_flags(&srgb, &srgb, Intent::Perceptual, Flags::NO_OPTIMIZE).unwrap();
+    let mut px = [[1u8, 2, 3]];
+    tr.transform_in_place(&mut px);
+
+    let context = ThreadContext::new();
+    let srgb = Profile::new_srgb_context(&context);
+    let tr = Transform::<RGB16, [u16; 3], _>::new_typed_context(&context, &srgb, &srgb, Intent::Perceptual).unwrap();
+    let mut dst = [[0u16; 3]];
+    tr.transform_pixels(&[RGB16 { r: 0xFFFF, g: 0, b: 0 }], &mut dst);
+    assert_eq!([0xFFFF, 0, 0], dst[0]);
+
+    let tr = Transform::<[u8; 3], [u8; 4], _, _>::new_typed_flags_context(&context, &srgb, &srgb, Intent::Perceptual, Flags::NO_CACHE).unwrap();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let mut dst = [[0u8; 4]];
+            tr.transform_pixels(&[[0, 255, 0]], &mut dst);
+            assert_eq!([0, 255, 0, 0], dst[0]);
+        });
+    });
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_pixel() {
+    #[derive(Copy, Clone, Debug, PartialEq, LcmsPixel)]
+    #[repr(C)]
+    #[lcms2(format = BGR_16)]
+    struct Bgr16 {
+        b: u16,
+        g: u16,
+        r: u16,
+    }
+    unsafe impl Pod for Bgr16 {}
+    unsafe impl Zeroable for Bgr16 {}
+
+    #[derive(Copy, Clone, LcmsPixel)]
+    #[repr(transparent)]
+    #[lcms2(format = PixelFormat::GRAY_FLT)]
+    struct Luma(f32);
+    unsafe impl Pod for Luma {}
+    unsafe impl Zeroable for Luma {}
+
+    assert_eq!(PixelFormat::BGR_16, Bgr16::FORMAT);
+    let srgb = Profile::new_srgb();
+    let tr = Transform::<[u8; 3], Bgr16>::new_typed(&srgb, &srgb, Intent::Perceptual).unwrap();
+    let mut dst = [Bgr16 { b: 0, g: 0, r: 0 }];
+    tr.transform_pixels(&[[255, 0, 0]], &mut dst);
+    assert_eq!(Bgr16 { b: 0, g: 0, r: 0xFFFF }, dst[0]);
+
+    let gray = Profile::new_icc(SGRAY_PROFILE).unwrap();
+    let tr = Transform::<Bgr16, Luma>::new_typed(&srgb, &gray, Intent::Perceptual).unwrap();
+    let mut dst = [Luma(0.)];
+    tr.transform_pixels(&[Bgr16 { b: 0xFFFF, g: 0xFFFF, r: 0xFFFF }], &mut dst);
+    assert!((dst[0].0 - 1.).abs() < 0.01);
+}
+
+#[cfg(feature = "rgb")]
+#[test]
+fn new_typed_rgb() {
+    use rgb::alt::{Gray, BGRA};
+    use rgb::RGB;
+
+    let srgb = Profile::new_srgb();
+    let tr = Transform::<RGB<u8>, BGRA<u16>>::new_typed(&srgb, &srgb, Intent::Perceptual).unwrap();
+    let mut dst = [BGRA { b: 0, g: 0, r: 0, a: 0 }];
+    tr.transform_pixels(&[RGB::new(0, 0, 255)], &mut dst);
+    assert_eq!(0xFFFF, dst[0].b);
+    assert_eq!(0, dst[0].r);
+
+    let gray = Profile::new_icc(SGRAY_PROFILE).unwrap();
+    let tr = Transform::<Gray<f32>, RGB<f64>>::new_typed(&gray, &srgb, Intent::Perceptual).unwrap();
+    let mut dst = [RGB::new(0., 0., 0.)];
+    tr.transform_pixels(&[Gray(1.)], &mut dst);
+    assert!((dst[0].g - 1.).abs() < 0.01);
+}